@@ -0,0 +1,251 @@
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use clap::Args;
+use glam::Vec3;
+use light::{
+    canvas::Canvas,
+    config::{IntegratorKind, CONFIG},
+    object::Object,
+    physics_constants::TIME_SPEED,
+    world::{
+        gravity::{
+            get_point_acceleration, get_total_energy, BarnesHutSolver, DirectSolver,
+            GpuGravitySolver, GravitySolver,
+        },
+        integrator::create_integrator,
+        GpuContext,
+    },
+};
+
+use crate::create_world;
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// RON scene file, a single galaxy if not set
+    #[arg(short, long)]
+    pub scene: Option<PathBuf>,
+
+    /// Amount of measured iterations
+    #[arg(short = 'n', long, default_value_t = 20)]
+    pub iterations: usize,
+
+    /// Random seed (`SEED`)
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Use the software wgpu adapter (`GPU_FALLBACK_ADAPTER`), e.g. on CI without gpu
+    #[arg(long)]
+    pub software_adapter: bool,
+}
+
+/// Largest relative error of the gpu gravity which is still considered correct (f32 rounding).
+const GPU_GRAVITY_TOLERANCE: f32 = 1e-4;
+
+/// Time and error of approximate gravity solvers compared to the exact one.
+fn bench_gravity(stars: &[Object], gpu_context: Option<&Arc<GpuContext>>) -> bool {
+    println!("Gravity ({} bodies):", stars.len());
+
+    let start_time = Instant::now();
+    let exact = get_reference_accelerations(stars, CONFIG.gravity_softening);
+    let reference_time = start_time.elapsed();
+    println!(
+        "  reference (original serial Vec3 loop): {:?}",
+        reference_time
+    );
+
+    let solvers = [
+        (
+            "direct serial (Vec3A)".to_string(),
+            DirectSolver::serial(CONFIG.gravity_softening),
+        ),
+        (
+            format!(
+                "direct parallel (Vec3A, {} threads)",
+                rayon::current_num_threads()
+            ),
+            DirectSolver::new(CONFIG.gravity_softening),
+        ),
+    ];
+    for (name, mut solver) in solvers {
+        let start_time = Instant::now();
+        let accelerations = solver.get_accelerations(stars);
+        let elapsed = start_time.elapsed();
+        println!(
+            "  {}: {:?}, {:.1}x faster than reference, relative error {:.2e}",
+            name,
+            elapsed,
+            reference_time.as_secs_f64() / elapsed.as_secs_f64(),
+            get_relative_error(&exact, &accelerations)
+        );
+    }
+
+    let gpu_matches = bench_gpu_gravity(stars, &exact, gpu_context);
+
+    for theta in [0.3, 0.5, 0.7, 1.0] {
+        let mut solver = BarnesHutSolver::new(theta, CONFIG.gravity_softening);
+
+        let start_time = Instant::now();
+        let accelerations = solver.get_accelerations(stars);
+        let elapsed = start_time.elapsed();
+
+        println!(
+            "  barnes-hut θ={}: {:?}, relative error {:.2e}",
+            theta,
+            elapsed,
+            get_relative_error(&exact, &accelerations)
+        );
+    }
+
+    gpu_matches
+}
+
+/// Direct sum exactly as `World` computed gravity before `DirectSolver` was parallelized,
+/// baseline of the solvers speedup.
+fn get_reference_accelerations(stars: &[Object], softening: f32) -> Vec<Vec3> {
+    stars
+        .iter()
+        .enumerate()
+        .map(|(i, body)| {
+            let mut a = Vec3::ZERO;
+
+            for (j, other) in stars.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+
+                a += get_point_acceleration(body.pos, other.pos, other.mass, softening);
+            }
+
+            a
+        })
+        .collect()
+}
+
+/// Compare the gpu solver with the exact cpu result, on the device of the world.
+fn bench_gpu_gravity(
+    stars: &[Object],
+    exact: &[Vec3],
+    gpu_context: Option<&Arc<GpuContext>>,
+) -> bool {
+    let context = match gpu_context {
+        Some(context) => context.clone(),
+        None => {
+            println!(
+                "  gpu: skipped, the world has no wgpu device (no adapter or LIGHT_BACKEND=cpu)"
+            );
+            return true;
+        }
+    };
+
+    let mut solver = GpuGravitySolver::new(context, CONFIG.gravity_softening);
+    // first run includes shader compilation and buffers creation
+    solver.get_accelerations(stars);
+
+    let start_time = Instant::now();
+    let accelerations = solver.get_accelerations(stars);
+    let elapsed = start_time.elapsed();
+
+    let error = get_relative_error(exact, &accelerations);
+    let matches = error < GPU_GRAVITY_TOLERANCE;
+    println!(
+        "  gpu: {:?}, relative error {:.2e}, matches cpu: {}",
+        elapsed, error, matches
+    );
+
+    matches
+}
+
+/// Relative energy drift of every integrator after the given amount of steps.
+fn bench_integrators(stars: &[Object], steps: usize) {
+    println!("Integrators ({} steps, direct gravity):", steps);
+
+    let mut gravity = DirectSolver::new(CONFIG.gravity_softening);
+    let initial_energy = get_total_energy(stars, CONFIG.gravity_softening);
+
+    for kind in [
+        IntegratorKind::Euler,
+        IntegratorKind::SemiImplicitEuler,
+        IntegratorKind::Leapfrog,
+        IntegratorKind::Rk4,
+    ] {
+        let mut integrator = create_integrator(kind);
+        let mut bodies = stars.to_vec();
+
+        let start_time = Instant::now();
+        for _ in 0..steps {
+            integrator.step(&mut bodies, &mut gravity, TIME_SPEED);
+        }
+        let elapsed = start_time.elapsed();
+
+        let drift = (get_total_energy(&bodies, CONFIG.gravity_softening) - initial_energy)
+            / initial_energy.abs();
+        println!("  {:?}: {:?}, energy drift {:.2e}", kind, elapsed, drift);
+    }
+}
+
+/// RMS of the difference divided by RMS of the exact accelerations.
+fn get_relative_error(exact: &[Vec3], approximate: &[Vec3]) -> f32 {
+    let (difference, total) = exact.iter().zip(approximate).fold(
+        (0.0, 0.0),
+        |(difference, total), (exact, approximate)| {
+            (
+                difference + (*exact - *approximate).length_squared(),
+                total + exact.length_squared(),
+            )
+        },
+    );
+
+    if total > 0.0 {
+        (difference / total).sqrt()
+    } else {
+        0.0
+    }
+}
+
+/// Average time of light and movement updates of the scene.
+///
+/// Returns false if a result is invalid (e.g. gpu gravity doesn't match the cpu one).
+pub fn bench_world(args: &BenchArgs) -> bool {
+    let software_adapter = args.software_adapter || CONFIG.gpu_fallback_adapter;
+    let mut world = match create_world(args.scene.as_ref(), args.seed, software_adapter) {
+        Some(world) => world,
+        None => return false,
+    };
+
+    let valid = bench_gravity(world.stars(), world.gpu_context());
+    bench_integrators(world.stars(), args.iterations);
+
+    let camera = world
+        .camera_settings()
+        .create_camera(world.get_center_of_mass());
+    let mut canvas = Canvas::new(
+        world.render_settings().width,
+        world.render_settings().height,
+    );
+
+    let mut light_time = Duration::ZERO;
+    let mut movement_time = Duration::ZERO;
+    for _ in 0..args.iterations {
+        let start_time = Instant::now();
+        world.update_light(camera.as_ref(), &mut canvas);
+        light_time += start_time.elapsed();
+
+        let start_time = Instant::now();
+        world.update_movement();
+        movement_time += start_time.elapsed();
+    }
+
+    let iterations = args.iterations.max(1) as u32;
+    println!(
+        "World ({} iterations, {:?} gravity):",
+        args.iterations, CONFIG.gravity_solver
+    );
+    println!("  light: {:?} per iteration", light_time / iterations);
+    println!("  movement: {:?} per iteration", movement_time / iterations);
+
+    valid
+}
@@ -0,0 +1,115 @@
+use std::{collections::HashSet, sync::mpsc::Receiver};
+
+use glam::{Quat, Vec2, Vec3};
+use show_image::event::{ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+use super::CameraModel;
+use crate::config::CONFIG;
+
+/// Radians per pixel of mouse drag.
+const MOUSE_SENSITIVITY: f32 = 0.005;
+
+/// Radians per iteration while an arrow key is pressed.
+const ROTATION_SPEED: f32 = 0.02;
+
+/// Focal length multiplier per mouse wheel line.
+const ZOOM_SPEED: f32 = 1.1;
+
+/// Moves camera according to keyboard and mouse input of the canvas window.
+///
+/// W/S/A/D - move forward/backward/left/right, Space/LShift - move up/down,
+/// arrows or mouse drag - rotate, mouse wheel - zoom.
+pub struct CameraController {
+    events: Receiver<WindowEvent>,
+    pressed: HashSet<VirtualKeyCode>,
+
+    /// mouse drag since the last update, in pixels
+    drag: Vec2,
+
+    /// mouse wheel scroll since the last update, in lines
+    scroll: f32,
+}
+
+impl CameraController {
+    pub fn new(events: Receiver<WindowEvent>) -> Self {
+        CameraController {
+            events,
+            pressed: HashSet::new(),
+            drag: Vec2::ZERO,
+            scroll: 0.0,
+        }
+    }
+
+    fn handle_events(&mut self) {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                WindowEvent::KeyboardInput(event) => {
+                    if let Some(key) = event.input.key_code {
+                        match event.input.state {
+                            ElementState::Pressed => self.pressed.insert(key),
+                            ElementState::Released => self.pressed.remove(&key),
+                        };
+                    }
+                }
+                WindowEvent::MouseMove(event) if event.buttons.is_pressed(MouseButton::Left) => {
+                    self.drag += event.position - event.prev_position;
+                }
+                WindowEvent::MouseWheel(event) => match event.delta {
+                    MouseScrollDelta::LineDelta(_, y) => self.scroll += y,
+                    MouseScrollDelta::PixelDelta(delta) => self.scroll += delta.y as f32 / 100.0,
+                },
+                _ => {}
+            }
+        }
+    }
+
+    /// Axis value from a pair of keys: 1 if only `positive` is pressed, -1 if only `negative`.
+    fn axis(&self, positive: VirtualKeyCode, negative: VirtualKeyCode) -> f32 {
+        let mut value = 0.0;
+        if self.pressed.contains(&positive) {
+            value += 1.0;
+        }
+        if self.pressed.contains(&negative) {
+            value -= 1.0;
+        }
+        value
+    }
+
+    /// Apply input received since the last call, return true if the camera was changed.
+    pub fn update(&mut self, camera: &mut dyn CameraModel) -> bool {
+        self.handle_events();
+
+        let movement = Vec3::new(
+            self.axis(VirtualKeyCode::D, VirtualKeyCode::A),
+            self.axis(VirtualKeyCode::Space, VirtualKeyCode::LShift),
+            self.axis(VirtualKeyCode::S, VirtualKeyCode::W),
+        );
+
+        let rotation = self.drag * MOUSE_SENSITIVITY
+            + Vec2::new(
+                self.axis(VirtualKeyCode::Right, VirtualKeyCode::Left),
+                self.axis(VirtualKeyCode::Down, VirtualKeyCode::Up),
+            ) * ROTATION_SPEED;
+
+        let zoom = ZOOM_SPEED.powf(self.scroll);
+
+        self.drag = Vec2::ZERO;
+        self.scroll = 0.0;
+
+        if movement == Vec3::ZERO && rotation == Vec2::ZERO && zoom == 1.0 {
+            return false;
+        }
+
+        camera.set_position(camera.position() + camera.rotation() * movement * CONFIG.camera_speed);
+
+        // yaw around world Y axis, pitch around camera's X axis
+        let camera_rotation = Quat::from_rotation_y(-rotation.x)
+            * camera.rotation()
+            * Quat::from_rotation_x(-rotation.y);
+        camera.set_rotation(camera_rotation.normalize());
+
+        camera.zoom(zoom);
+
+        true
+    }
+}
@@ -0,0 +1,227 @@
+use glam::{Quat, Vec2, Vec3, Vec3Swizzles};
+
+use super::{CameraModel, CameraParams};
+use crate::photons::Photon;
+
+/// Largest focal length as a fraction of `focus_distance`,
+/// at `focus_distance` itself the image distance is infinite.
+const MAX_FOCAL_LENGTH_RATIO: f32 = 0.99;
+
+/// ### Thin lens camera.
+/// Camera's sensor - is a rectangle, in camera space it belongs to XY plane faced to -Z direction,
+/// the lens is parallel to the sensor at `(0, 0, -image_distance)`.
+/// Objects at `focus_distance` from the lens are sharp, others are blurred the more the larger the aperture is.
+#[derive(Debug, Clone, Copy)]
+pub struct LensCamera {
+    /// Radius of the lens. The larger the aperture, the more light will pass through, but the shallower depth of field will be.
+    pub aperture_radius: f32,
+
+    /// Focal length of the lens
+    pub focal_length: f32,
+
+    /// Distance between the lens and the plane which is in focus, must be greater than `focal_length`
+    pub focus_distance: f32,
+
+    /// Size of the sensor rectangle.
+    pub sensor_size: Vec2,
+
+    /// Position of the sensor center in world space.
+    pub position: Vec3,
+
+    /// Rotation from camera space to world space.
+    pub rotation: Quat,
+}
+
+impl LensCamera {
+    /// Create a camera at the origin looking to -Z direction.
+    /// `focal_length` is clamped to be less than `focus_distance`.
+    pub fn new(
+        aperture_radius: f32,
+        focal_length: f32,
+        focus_distance: f32,
+        sensor_size: Vec2,
+    ) -> LensCamera {
+        LensCamera {
+            aperture_radius,
+            focal_length: focal_length.min(focus_distance * MAX_FOCAL_LENGTH_RATIO),
+            focus_distance,
+            sensor_size,
+            position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+        }
+    }
+
+    /// Distance between the lens and the sensor, from the thin lens equation: 1/f = 1/d_o + 1/d_i
+    pub fn get_image_distance(&self) -> f32 {
+        1.0 / (1.0 / self.focal_length - 1.0 / self.focus_distance)
+    }
+}
+
+impl CameraModel for LensCamera {
+    fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+    }
+
+    fn rotation(&self) -> Quat {
+        self.rotation
+    }
+
+    fn set_rotation(&mut self, rotation: Quat) {
+        self.rotation = rotation;
+    }
+
+    /// Focal length can't reach `focus_distance`, otherwise the image would be behind the lens.
+    fn zoom(&mut self, factor: f32) {
+        self.focal_length =
+            (self.focal_length * factor).min(self.focus_distance * MAX_FOCAL_LENGTH_RATIO);
+    }
+
+    /// The photon is refracted by the lens, the segment must cross the sensor plane.
+    fn get_intersection(&self, photon: Photon) -> Option<(Vec2, f32)> {
+        let (pos, dir) = self.to_camera_space(photon.get_position(), photon.get_direction());
+        let dir = dir.normalize();
+
+        if pos.z.is_sign_positive() || (pos.z + dir.z).is_sign_negative() {
+            return None;
+        }
+
+        let image_distance = self.get_image_distance();
+
+        // point where the photon crosses the lens plane
+        let lens_overlap_position = pos + dir * ((-image_distance - pos.z) / dir.z);
+        let lens_overlap_uv = lens_overlap_position.xy() / self.aperture_radius;
+
+        let dist_sq = lens_overlap_uv.length_squared();
+        if dist_sq > 1. {
+            return None;
+        }
+
+        // thin lens changes the slope of the ray proportionally to the distance from its center
+        let slope = dir.xy() / dir.z - lens_overlap_position.xy() / self.focal_length;
+        let sensor_overlap_position = lens_overlap_position.xy() + slope * image_distance;
+
+        let uv = sensor_overlap_position / self.sensor_size;
+
+        // check if overlap point is belongs to sensor rectangle
+        if uv.x.abs() > 0.5 || uv.y.abs() > 0.5 {
+            return None;
+        }
+
+        Some((uv + 0.5, dist_sq))
+    }
+
+    fn get_shader_params(&self) -> CameraParams {
+        CameraParams {
+            position: self.position.extend(0.0).to_array(),
+            inverse_rotation: self.rotation.inverse().to_array(),
+            sensor_size: self.sensor_size.to_array(),
+            kind: 1,
+            aperture_radius: self.aperture_radius,
+            focal_length: self.focal_length,
+            image_distance: self.get_image_distance(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::photons::wavelength::WaveLength;
+
+    /// Sensor uv of the ray from `point` through `lens_point` (relative to the aperture radius) of the lens.
+    fn get_hit(camera: &LensCamera, point: Vec3, lens_point: Vec2) -> Vec2 {
+        let image_distance = camera.get_image_distance();
+        let lens_point = (lens_point * camera.aperture_radius).extend(-image_distance);
+        let dir = (lens_point - point).normalize();
+
+        // the photon is right in front of the sensor
+        let pos = point + dir * ((-0.1 - point.z) / dir.z);
+        let photon = Photon::new(WaveLength::WHITE, pos, dir, 10);
+
+        let (uv, _) = camera
+            .get_intersection(photon)
+            .expect("photon hits the sensor");
+        uv
+    }
+
+    /// Points on the lens from its center to the edge.
+    const LENS_POINTS: [Vec2; 5] = [
+        Vec2::ZERO,
+        Vec2::new(0.9, 0.0),
+        Vec2::new(-0.9, 0.0),
+        Vec2::new(0.0, 0.5),
+        Vec2::new(-0.4, -0.6),
+    ];
+
+    #[test]
+    fn focus_plane_is_sharp() {
+        let camera = LensCamera::new(0.5, 1.0, 8.0, Vec2::new(2.0, 2.0));
+        let image_distance = camera.get_image_distance();
+        let point = Vec3::new(1.5, -0.8, -image_distance - camera.focus_distance);
+
+        // image is flipped and scaled by the magnification of the lens
+        let expected = -point.xy() * image_distance / camera.focus_distance / camera.sensor_size
+            + Vec2::splat(0.5);
+
+        for lens_point in LENS_POINTS {
+            let uv = get_hit(&camera, point, lens_point);
+            assert!(
+                uv.abs_diff_eq(expected, 1e-5),
+                "lens point {}: {} != {}",
+                lens_point,
+                uv,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn blur_grows_with_aperture() {
+        let get_blur = |aperture_radius: f32, distance: f32| {
+            let camera = LensCamera::new(aperture_radius, 1.0, 8.0, Vec2::new(2.0, 2.0));
+            let point = Vec3::new(0.5, 0.5, -camera.get_image_distance() - distance);
+
+            let left = get_hit(&camera, point, Vec2::new(-0.9, 0.0));
+            let right = get_hit(&camera, point, Vec2::new(0.9, 0.0));
+            (right - left).length()
+        };
+
+        // behind and in front of the focus plane
+        for distance in [16.0, 4.0] {
+            let blur = get_blur(0.1, distance);
+            assert!(blur > 1e-3, "distance {}: blur {}", distance, blur);
+
+            for factor in [2.0, 4.0] {
+                let wider = get_blur(0.1 * factor, distance);
+                assert!(
+                    (wider / blur - factor).abs() < 1e-3,
+                    "distance {}: blur {} with {} times larger aperture, {} without",
+                    distance,
+                    wider,
+                    factor,
+                    blur
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn zoom_keeps_image_distance_finite() {
+        let mut camera = LensCamera::new(0.1, 1.0, 8.0, Vec2::new(2.0, 2.0));
+        for _ in 0..100 {
+            camera.zoom(1.1);
+        }
+
+        assert!(camera.focal_length < camera.focus_distance);
+        let image_distance = camera.get_image_distance();
+        assert!(image_distance.is_finite() && image_distance > 0.0);
+
+        let camera = LensCamera::new(0.1, 10.0, 8.0, Vec2::new(2.0, 2.0));
+        assert!(camera.get_image_distance() > 0.0);
+    }
+}
@@ -1,9 +1,103 @@
-use glam::{Vec2, Vec3Swizzles};
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat3, Quat, Vec2, Vec3, Vec3Swizzles};
 
 use crate::photons::Photon;
 
+pub mod controller;
+mod lens;
+
+pub use self::lens::LensCamera;
+
+/// Camera's parameters as they are passed to `shader.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable, Default)]
+pub struct CameraParams {
+    pub position: [f32; 4],
+    /// rotation from world space to camera space
+    pub inverse_rotation: [f32; 4],
+    pub sensor_size: [f32; 2],
+    /// 0 - camera obscura, 1 - thin lens
+    pub kind: u32,
+    /// hole radius or lens aperture radius
+    pub aperture_radius: f32,
+    pub focal_length: f32,
+    /// distance between lens and sensor (thin lens only)
+    pub image_distance: f32,
+    pub _pad: [f32; 2],
+}
+
+/// Optical model of a camera, placed in world space by `position` and `rotation`.
+/// In camera space the sensor is a rectangle which belongs to XY plane faced to -Z direction.
+pub trait CameraModel: Send + Sync {
+    fn position(&self) -> Vec3;
+
+    fn set_position(&mut self, position: Vec3);
+
+    /// Rotation from camera space to world space.
+    fn rotation(&self) -> Quat;
+
+    fn set_rotation(&mut self, rotation: Quat);
+
+    /// Multiply focal length by `factor`.
+    fn zoom(&mut self, factor: f32);
+
+    /// Return intersection between camera's sensor and a photon's step (if no intersection - return None) and accuracy (0 - photon passed through the center of the hole/lens, 1 - through the edge).
+    fn get_intersection(&self, photon: Photon) -> Option<(Vec2, f32)>;
+
+    fn get_shader_params(&self) -> CameraParams;
+
+    /// Rotate the camera to look at `target`, `up` defines where the top of the sensor is.
+    /// Keeps the rotation if `target` is the camera position,
+    /// picks any sensor top if `up` is parallel to the view direction.
+    fn look_at(&mut self, target: Vec3, up: Vec3) {
+        let forward = (target - self.position()).normalize_or_zero();
+        if forward == Vec3::ZERO {
+            return;
+        }
+
+        let right = forward.cross(up);
+        let right = if right.length_squared() > 1e-12 {
+            right.normalize()
+        } else {
+            forward.any_orthonormal_vector()
+        };
+        let up = right.cross(forward);
+
+        self.set_rotation(Quat::from_mat3(&Mat3::from_cols(right, up, -forward)).normalize());
+    }
+
+    /// Direction the camera is looking at in world space.
+    fn forward(&self) -> Vec3 {
+        self.rotation() * Vec3::NEG_Z
+    }
+
+    fn right(&self) -> Vec3 {
+        self.rotation() * Vec3::X
+    }
+
+    fn up(&self) -> Vec3 {
+        self.rotation() * Vec3::Y
+    }
+
+    /// Transform a point from world space to camera space.
+    fn world_to_camera(&self, point: Vec3) -> Vec3 {
+        self.rotation().inverse() * (point - self.position())
+    }
+
+    /// Transform a point from camera space to world space.
+    fn camera_to_world(&self, point: Vec3) -> Vec3 {
+        self.rotation() * point + self.position()
+    }
+
+    /// Transform a photon's position and direction from world space to camera space.
+    fn to_camera_space(&self, pos: Vec3, dir: Vec3) -> (Vec3, Vec3) {
+        (self.world_to_camera(pos), self.rotation().inverse() * dir)
+    }
+}
+
 /// ### Camera obscura.
-/// Camera's sensor - is a rectangle, belongs to XY plane faced to -Z direction.
+/// Camera's sensor - is a rectangle, in camera space it belongs to XY plane faced to -Z direction,
+/// the hole is at `(0, 0, -focal_length)`.
 #[derive(Debug, Clone, Copy)]
 pub struct Camera {
     /// pow 2 of Camera's hole radius. The larger the hole, the more light will pass through, but the less sharp the image will be.
@@ -14,21 +108,59 @@ pub struct Camera {
 
     /// Size of the sensor rectangle.
     pub sensor_size: Vec2,
+
+    /// Position of the sensor center in world space.
+    pub position: Vec3,
+
+    /// Rotation from camera space to world space.
+    pub rotation: Quat,
 }
 
 impl Camera {
-    /// Return intersection between camera's sensor and a ray segment (if no intersection - return None) and accuracy (squared distance between center of the hole and hole intersection point).
+    /// Create a camera at the origin looking to -Z direction.
+    pub fn new(hole_radius: f32, focal_length: f32, sensor_size: Vec2) -> Camera {
+        Camera {
+            hole_radius,
+            focal_length,
+            sensor_size,
+            position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+        }
+    }
+}
+
+impl CameraModel for Camera {
+    fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+    }
+
+    fn rotation(&self) -> Quat {
+        self.rotation
+    }
+
+    fn set_rotation(&mut self, rotation: Quat) {
+        self.rotation = rotation;
+    }
+
+    fn zoom(&mut self, factor: f32) {
+        self.focal_length *= factor;
+    }
+
     /// The segment must belong to a straight line passing through hole (if it is not - return None).  
-    pub fn get_intersection(&self, photon: Photon) -> Option<(Vec2, f32)> {
-        let pos = photon.get_position();
-        let dir = photon.get_direction().normalize();
+    fn get_intersection(&self, photon: Photon) -> Option<(Vec2, f32)> {
+        let (pos, dir) = self.to_camera_space(photon.get_position(), photon.get_direction());
+        let dir = dir.normalize();
 
         if pos.z.is_sign_positive() || (pos.z + dir.z).is_sign_negative() {
             return None;
         }
 
         let factor = -pos.z;
-        let sensor_overlap_position = pos + dir / dir.z / self.focal_length * factor;
+        let sensor_overlap_position = pos + dir / dir.z * factor;
 
         let uv = sensor_overlap_position.xy() / self.sensor_size;
 
@@ -45,6 +177,75 @@ impl Camera {
             return None;
         }
 
-        return Some((uv + 0.5, dist_sq));
+        Some((uv + 0.5, dist_sq))
+    }
+
+    fn get_shader_params(&self) -> CameraParams {
+        CameraParams {
+            position: self.position.extend(0.0).to_array(),
+            inverse_rotation: self.rotation.inverse().to_array(),
+            sensor_size: self.sensor_size.to_array(),
+            kind: 0,
+            aperture_radius: self.hole_radius,
+            focal_length: self.focal_length,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::photons::wavelength::WaveLength;
+
+    #[test]
+    fn zoomed_pinhole_projects_through_hole_center() {
+        let mut camera = Camera::new(0.01, 1.0, Vec2::new(2.0, 2.0));
+        camera.zoom(2.0);
+
+        // ray from the hole center (0, 0, -2) to the sensor point (-0.4, 0.2, 0)
+        let sensor_point = Vec3::new(-0.4, 0.2, 0.0);
+        let dir = (sensor_point - Vec3::new(0.0, 0.0, -2.0)).normalize();
+        let photon = Photon::new(WaveLength::WHITE, sensor_point - dir * 0.3, dir, 10);
+
+        let (uv, accuracy) = camera
+            .get_intersection(photon)
+            .expect("Photon passes through the hole");
+        assert!(uv.abs_diff_eq(Vec2::new(0.3, 0.6), 1e-5), "uv {}", uv);
+        assert!(accuracy < 1e-6, "accuracy {}", accuracy);
+    }
+
+    fn create_camera() -> Camera {
+        let mut camera = Camera::new(0.01, 1.0, Vec2::new(2.0, 2.0));
+        camera.set_position(Vec3::new(1.0, 2.0, 3.0));
+        camera
+    }
+
+    #[test]
+    fn look_at_points_forward_to_target() {
+        let mut camera = create_camera();
+        camera.look_at(Vec3::new(1.0, 2.0, -7.0), Vec3::X);
+
+        assert!(camera.forward().abs_diff_eq(Vec3::NEG_Z, 1e-6));
+        assert!((camera.rotation() * Vec3::Y).abs_diff_eq(Vec3::X, 1e-6));
+    }
+
+    #[test]
+    fn look_at_along_up_is_finite() {
+        let mut camera = create_camera();
+        camera.look_at(Vec3::new(1.0, 12.0, 3.0), Vec3::Y);
+
+        assert!(camera.rotation().is_finite());
+        assert!(camera.forward().abs_diff_eq(Vec3::Y, 1e-6));
+    }
+
+    #[test]
+    fn look_at_own_position_keeps_rotation() {
+        let mut camera = create_camera();
+        let rotation = Quat::from_rotation_y(0.5);
+        camera.set_rotation(rotation);
+        camera.look_at(camera.position(), Vec3::Y);
+
+        assert_eq!(camera.rotation(), rotation);
     }
 }
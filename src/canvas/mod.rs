@@ -1,25 +1,57 @@
-use std::path::Path;
+use std::{path::Path, sync::mpsc::Receiver};
 
-use glam::Vec2;
-use image::{ImageBuffer, LumaA, RgbImage};
+use glam::{Vec2, Vec3};
+use image::{ImageBuffer, Rgb, RgbImage};
 use show_image::{
-    create_window, error::SetImageError, ImageInfo, ImageView, WindowOptions, WindowProxy,
+    create_window,
+    error::{InvalidWindowId, SetImageError},
+    event::WindowEvent,
+    ImageInfo, ImageView, WindowOptions, WindowProxy,
 };
 
-use crate::{config::CONFIG, photons::wavelength::WaveLength};
+use crate::{
+    config::CONFIG,
+    photons::{
+        cie::{encode_srgb, xyz_to_linear_srgb},
+        wavelength::WaveLength,
+    },
+};
+
+use self::{
+    raw::{convert_raw, save_raw, RawColorSpace, RawFormat},
+    tone_mapping::get_auto_exposure,
+};
+
+pub mod raw;
+pub mod tone_mapping;
+pub mod video;
 
 pub struct Canvas {
-    img: ImageBuffer<LumaA<f32>, Vec<f32>>,
-    window: WindowProxy,
+    /// Accumulated CIE XYZ tristimulus of all photons hit each pixel
+    img: ImageBuffer<Rgb<f32>, Vec<f32>>,
+    /// Window to show the image in, None in headless mode
+    window: Option<WindowProxy>,
 }
 
 impl Canvas {
+    /// Create a canvas which only accumulates light and saves images, without any window.
     pub fn new(width: u32, height: u32) -> Canvas {
+        Canvas {
+            img: ImageBuffer::new(width, height),
+            window: None,
+        }
+    }
+
+    /// Create a canvas which also shows the image in a window.
+    /// Requires a running `show_image` context.
+    pub fn with_window(width: u32, height: u32) -> Canvas {
         let window = create_window(
             "image",
             WindowOptions {
                 background_color: show_image::Color::white(),
                 size: Some([width * 3, height * 3]),
+                // in realtime mode mouse is used to move the camera instead of the image
+                default_controls: !CONFIG.realtime,
                 ..Default::default()
             },
         )
@@ -27,18 +59,49 @@ impl Canvas {
 
         Canvas {
             img: ImageBuffer::new(width, height),
-            window,
+            window: Some(window),
         }
     }
 
+    pub fn width(&self) -> u32 {
+        self.img.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.img.height()
+    }
+
+    /// Remove everything drawn so far, e.g. after the camera has moved.
+    pub fn clear(&mut self) {
+        for pixel in self.img.pixels_mut() {
+            *pixel = Rgb([0.0, 0.0, 0.0]);
+        }
+    }
+
+    /// Keyboard and mouse events of the canvas window, None if there is no window.
+    pub fn window_events(&self) -> Option<Result<Receiver<WindowEvent>, InvalidWindowId>> {
+        self.window.as_ref().map(|window| window.event_channel())
+    }
+
     pub fn update_pixel(&mut self, x: u32, y: u32, wave_length: WaveLength, luminosity: f32) {
+        self.update_pixel_xyz(x, y, wave_length.to_xyz() * luminosity);
+    }
+
+    /// Add CIE XYZ tristimulus to the pixel.
+    pub fn update_pixel_xyz(&mut self, x: u32, y: u32, xyz: Vec3) {
         if x >= self.img.width() || y >= self.img.height() {
             return;
         }
 
         let val = &mut self.img.get_pixel_mut(x, self.img.height() - y - 1).0;
-        val[0] = (val[0] * val[1] + wave_length.0 * luminosity) / (val[1] + luminosity);
-        val[1] += luminosity;
+        val[0] += xyz.x;
+        val[1] += xyz.y;
+        val[2] += xyz.z;
+    }
+
+    /// Accumulated CIE XYZ tristimulus of the pixel.
+    pub fn get_pixel_xyz(&self, x: u32, y: u32) -> Vec3 {
+        Vec3::from(self.img.get_pixel(x, self.img.height() - y - 1).0)
     }
 
     pub fn update_pixel_by_uv(&mut self, uv: Vec2, wave_length: WaveLength, luminosity: f32) {
@@ -51,16 +114,26 @@ impl Canvas {
         self.update_pixel(x, y, wave_length, luminosity);
     }
 
+    /// Multiplier applied to the accumulated light before tone mapping.
+    pub fn get_exposure(&self) -> f32 {
+        let exposure = CONFIG.exposure.exp2();
+
+        if CONFIG.auto_exposure {
+            exposure * get_auto_exposure(&self.img, CONFIG.auto_exposure_percentile)
+        } else {
+            exposure
+        }
+    }
+
     pub fn generate_rgb(&self) -> RgbImage {
         let mut rgb_img = RgbImage::new(self.img.width(), self.img.height());
+        let exposure = self.get_exposure();
 
         for (x, y, pixel) in self.img.enumerate_pixels() {
-            let mut rgb_pixel = image::Rgb(WaveLength(pixel.0[0]).into());
-            rgb_pixel.0[0] = (rgb_pixel.0[0] as f32 * pixel.0[1]) as u8;
-            rgb_pixel.0[1] = (rgb_pixel.0[1] as f32 * pixel.0[1]) as u8;
-            rgb_pixel.0[2] = (rgb_pixel.0[2] as f32 * pixel.0[1]) as u8;
-
-            rgb_img.put_pixel(x, y, rgb_pixel);
+            let color = xyz_to_linear_srgb(Vec3::from(pixel.0)) * exposure;
+            let color = CONFIG.tone_mapping.apply(color, CONFIG.tone_mapping_white);
+            let rgb = encode_srgb(color) * 255.0;
+            rgb_img.put_pixel(x, y, Rgb([rgb.x as u8, rgb.y as u8, rgb.z as u8]));
         }
 
         rgb_img
@@ -73,23 +146,64 @@ impl Canvas {
         self.generate_rgb().save(path).expect("Image saved");
     }
 
+    /// Save raw float sensor data without any clipping or quantization.
+    pub fn save_raw<T>(&self, path: T, format: RawFormat, color_space: RawColorSpace)
+    where
+        T: AsRef<Path>,
+    {
+        save_raw(&convert_raw(&self.img, color_space), path, format);
+    }
+
     pub fn update_fading(&mut self) {
         for x in 0..self.img.width() {
             for y in 0..self.img.height() {
-                let mut color = self.img.get_pixel_mut(x, y);
-                color.0[1] = color[1] as f32 * CONFIG.fade_out_speed;
+                let color = self.img.get_pixel_mut(x, y);
+                for value in color.0.iter_mut() {
+                    *value *= CONFIG.fade_out_speed;
+                }
             }
         }
     }
 
+    /// Show the image in the window, do nothing if there is no window.
     pub fn show(&mut self) -> Result<(), SetImageError> {
+        let window = match &self.window {
+            Some(window) => window,
+            None => return Ok(()),
+        };
+
         let rgb_data = &self.generate_rgb();
         let rgb = ImageView::new(
             ImageInfo::rgb8(self.img.width(), self.img.height()),
             rgb_data,
         );
-        self.window.set_image("frame-001", rgb)?;
+        window.set_image("frame-001", rgb)?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_accumulates_and_fades() {
+        let mut canvas = Canvas::new(4, 4);
+        let red = WaveLength::new(650.0);
+        let green = WaveLength::new(530.0);
+
+        canvas.update_pixel(1, 2, red, 2.0);
+        canvas.update_pixel(1, 2, green, 0.5);
+
+        let expected = red.to_xyz() * 2.0 + green.to_xyz() * 0.5;
+        assert!(canvas.get_pixel_xyz(1, 2).abs_diff_eq(expected, 1e-6));
+        assert_eq!(canvas.get_pixel_xyz(2, 1), Vec3::ZERO);
+
+        canvas.update_fading();
+
+        let faded = expected * CONFIG.fade_out_speed;
+        assert!(canvas.get_pixel_xyz(1, 2).abs_diff_eq(faded, 1e-6));
+        assert!(canvas.get_pixel_xyz(1, 2).y < expected.y);
+    }
+}
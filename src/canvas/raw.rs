@@ -0,0 +1,152 @@
+use std::{fs::File, io::BufWriter, path::Path, str::FromStr};
+
+use glam::Vec3;
+use image::{ImageFormat, Rgb32FImage};
+use tiff::encoder::{colortype::RGB32Float, TiffEncoder};
+
+use crate::photons::cie::xyz_to_linear_srgb;
+
+/// File format for the raw (float, not tone mapped) sensor data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawFormat {
+    /// OpenEXR, 32-bit float channels
+    Exr,
+    /// TIFF, 32-bit float channels
+    Tiff,
+}
+
+impl RawFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RawFormat::Exr => "exr",
+            RawFormat::Tiff => "tiff",
+        }
+    }
+}
+
+impl FromStr for RawFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "exr" => Ok(RawFormat::Exr),
+            "tiff" | "tif" => Ok(RawFormat::Tiff),
+            _ => Err(format!("Unknown raw format: {}", s)),
+        }
+    }
+}
+
+/// Color space of the raw sensor data channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawColorSpace {
+    /// CIE XYZ tristimulus exactly as accumulated by the sensor
+    Xyz,
+    /// Linear sRGB (D65), may contain negative values for out of gamut colors
+    LinearSrgb,
+}
+
+impl FromStr for RawColorSpace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "xyz" => Ok(RawColorSpace::Xyz),
+            "linear-srgb" | "srgb" => Ok(RawColorSpace::LinearSrgb),
+            _ => Err(format!("Unknown raw color space: {}", s)),
+        }
+    }
+}
+
+/// Convert accumulated XYZ sensor data into the given color space.
+pub fn convert_raw(img: &Rgb32FImage, color_space: RawColorSpace) -> Rgb32FImage {
+    let mut result = img.clone();
+
+    if color_space == RawColorSpace::LinearSrgb {
+        for pixel in result.pixels_mut() {
+            pixel.0 = xyz_to_linear_srgb(Vec3::from(pixel.0)).to_array();
+        }
+    }
+
+    result
+}
+
+pub fn save_raw<T>(img: &Rgb32FImage, path: T, format: RawFormat)
+where
+    T: AsRef<Path>,
+{
+    match format {
+        RawFormat::Exr => img
+            .save_with_format(path, ImageFormat::OpenExr)
+            .expect("Raw image saved"),
+        RawFormat::Tiff => {
+            let file = BufWriter::new(File::create(path).expect("Raw image file created"));
+            TiffEncoder::new(file)
+                .expect("Tiff encoder created")
+                .write_image::<RGB32Float>(img.width(), img.height(), img.as_raw())
+                .expect("Raw image saved");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+
+    fn create_image() -> Rgb32FImage {
+        Rgb32FImage::from_fn(3, 2, |x, y| {
+            image::Rgb([x as f32 * 0.5, y as f32 + 1e-3, -1.5 + 1e6 * x as f32])
+        })
+    }
+
+    /// Offset of the only strip of a little endian TIFF, from the StripOffsets tag of the first IFD.
+    fn get_strip_offset(bytes: &[u8]) -> usize {
+        const STRIP_OFFSETS: u16 = 273;
+        const SHORT: u16 = 3;
+
+        let read_u16 = |at: usize| u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap());
+        let read_u32 = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+
+        let ifd = read_u32(4) as usize;
+        let entry = (0..read_u16(ifd) as usize)
+            .map(|i| ifd + 2 + i * 12)
+            .find(|entry| read_u16(*entry) == STRIP_OFFSETS)
+            .expect("StripOffsets tag");
+
+        assert_eq!(read_u32(entry + 4), 1, "single strip");
+        match read_u16(entry + 2) {
+            SHORT => read_u16(entry + 8) as usize,
+            _ => read_u32(entry + 8) as usize,
+        }
+    }
+
+    #[test]
+    fn exr_round_trip_keeps_floats() {
+        let img = create_image();
+        let path = temp_path("raw.exr");
+
+        save_raw(&img, &path, RawFormat::Exr);
+        let loaded = image::open(&path).unwrap().into_rgb32f();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, img);
+    }
+
+    #[test]
+    fn tiff_stores_floats_in_strip() {
+        let img = create_image();
+        let path = temp_path("raw.tiff");
+
+        save_raw(&img, &path, RawFormat::Tiff);
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(&bytes[..4], b"II*\0");
+        let offset = get_strip_offset(&bytes);
+        let payload: Vec<f32> = bytes[offset..offset + img.as_raw().len() * 4]
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(&payload, img.as_raw());
+    }
+}
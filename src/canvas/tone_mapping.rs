@@ -0,0 +1,159 @@
+use std::str::FromStr;
+
+use glam::Vec3;
+use image::Rgb32FImage;
+
+/// Range of the auto exposure histogram in log2 of luminance.
+const HISTOGRAM_MIN_LOG: f32 = -20.0;
+const HISTOGRAM_MAX_LOG: f32 = 20.0;
+const HISTOGRAM_BINS: usize = 512;
+
+/// Operator which maps unbounded linear color into displayable 0..1 range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapping {
+    /// Only exposure, everything above 1 is clipped
+    Linear,
+    /// Extended Reinhard, `white` is mapped to 1
+    Reinhard,
+    /// Narkowicz fit of the ACES filmic curve
+    Aces,
+    /// Logarithmic curve, `white` is mapped to 1
+    Logarithmic,
+}
+
+impl FromStr for ToneMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "linear" => Ok(ToneMapping::Linear),
+            "reinhard" => Ok(ToneMapping::Reinhard),
+            "aces" => Ok(ToneMapping::Aces),
+            "log" | "logarithmic" => Ok(ToneMapping::Logarithmic),
+            _ => Err(format!("Unknown tone mapping: {}", s)),
+        }
+    }
+}
+
+impl ToneMapping {
+    /// Map exposed linear color, result may still need clamping to 0..1.
+    pub fn apply(&self, color: Vec3, white: f32) -> Vec3 {
+        let color = color.max(Vec3::ZERO);
+
+        match self {
+            ToneMapping::Linear => color,
+            ToneMapping::Reinhard => {
+                color * (Vec3::ONE + color / (white * white)) / (Vec3::ONE + color)
+            }
+            ToneMapping::Aces => {
+                (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14)
+            }
+            ToneMapping::Logarithmic => {
+                let scale = 1.0 / (1.0 + white).ln();
+                Vec3::new(
+                    color.x.ln_1p() * scale,
+                    color.y.ln_1p() * scale,
+                    color.z.ln_1p() * scale,
+                )
+            }
+        }
+    }
+}
+
+/// Exposure multiplier which maps luminance (CIE Y) of the `percentile` of lit pixels to 1.
+///
+/// Luminance is collected into a log2 histogram, empty pixels are ignored so a mostly black sky
+/// doesn't drag the exposure up. Returns 1 if nothing is lit yet.
+pub fn get_auto_exposure(img: &Rgb32FImage, percentile: f32) -> f32 {
+    let mut histogram = [0usize; HISTOGRAM_BINS];
+    let mut lit_pixels = 0;
+    let bin_size = (HISTOGRAM_MAX_LOG - HISTOGRAM_MIN_LOG) / HISTOGRAM_BINS as f32;
+
+    for pixel in img.pixels() {
+        let luminance = pixel.0[1];
+        if luminance <= 0.0 || !luminance.is_finite() {
+            continue;
+        }
+
+        let bin = ((luminance.log2() - HISTOGRAM_MIN_LOG) / bin_size)
+            .clamp(0.0, (HISTOGRAM_BINS - 1) as f32) as usize;
+        histogram[bin] += 1;
+        lit_pixels += 1;
+    }
+
+    if lit_pixels == 0 {
+        return 1.0;
+    }
+
+    let target = (lit_pixels as f32 * percentile.clamp(0.0, 1.0)).ceil() as usize;
+    let mut count = 0;
+    for (bin, amount) in histogram.iter().enumerate() {
+        count += amount;
+        if count >= target.max(1) {
+            // upper edge of the bin, so the chosen pixels are not clipped
+            let luminance = (HISTOGRAM_MIN_LOG + (bin + 1) as f32 * bin_size).exp2();
+            return 1.0 / luminance;
+        }
+    }
+
+    1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_are_monotonic_and_keep_black() {
+        let white = 4.0;
+        for mapping in [
+            ToneMapping::Reinhard,
+            ToneMapping::Aces,
+            ToneMapping::Logarithmic,
+        ] {
+            assert_eq!(
+                mapping.apply(Vec3::ZERO, white),
+                Vec3::ZERO,
+                "{:?}",
+                mapping
+            );
+
+            let mut previous = 0.0;
+            for i in 1..=1000 {
+                let value = mapping.apply(Vec3::splat(i as f32 * 0.01), white).x;
+                assert!(value > previous, "{:?} at {}", mapping, i);
+                previous = value;
+            }
+        }
+
+        assert!((ToneMapping::Reinhard.apply(Vec3::splat(white), white).x - 1.0).abs() < 1e-6);
+        assert!((ToneMapping::Logarithmic.apply(Vec3::splat(white), white).x - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn auto_exposure_maps_percentile_to_one() {
+        // 100 lit pixels with luminance 0.1..10 and black ones which must be ignored
+        let img = Rgb32FImage::from_fn(20, 10, |x, y| {
+            let i = y * 20 + x;
+            if i % 2 == 0 {
+                image::Rgb([0.0; 3])
+            } else {
+                let luminance = (i / 2 + 1) as f32 * 0.1;
+                image::Rgb([luminance; 3])
+            }
+        });
+
+        let exposure = get_auto_exposure(&img, 0.9);
+
+        // the 90th pixel, up to the histogram bin size
+        let mapped = 9.0 * exposure;
+        assert!(mapped <= 1.0 && mapped > 0.94, "mapped {}", mapped);
+    }
+
+    #[test]
+    fn auto_exposure_of_black_image_is_one() {
+        let img = Rgb32FImage::new(4, 4);
+
+        assert_eq!(get_auto_exposure(&img, 0.9), 1.0);
+    }
+}
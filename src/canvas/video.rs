@@ -0,0 +1,211 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, DynamicImage, Frame, RgbImage,
+};
+
+/// Container of the animation written by `VideoWriter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoFormat {
+    /// Looped animated gif, colors are quantized to 256 per frame
+    Gif,
+    /// Uncompressed YUV4MPEG2 (4:2:0) stream, e.g. for `ffmpeg -i video.y4m video.mp4`
+    Y4m,
+}
+
+impl VideoFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            VideoFormat::Gif => "gif",
+            VideoFormat::Y4m => "y4m",
+        }
+    }
+}
+
+impl FromStr for VideoFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "gif" => Ok(VideoFormat::Gif),
+            "y4m" => Ok(VideoFormat::Y4m),
+            _ => Err(format!("Unknown video format: {}", s)),
+        }
+    }
+}
+
+enum VideoEncoder {
+    // gif is written without a buffer, so the file stays playable if the simulation is killed
+    Gif(GifEncoder<File>),
+    Y4m(BufWriter<File>),
+}
+
+/// Appends frames to a single animation file as they are rendered.
+pub struct VideoWriter {
+    encoder: VideoEncoder,
+    width: u32,
+    height: u32,
+    fps: u32,
+}
+
+impl VideoWriter {
+    pub fn new<T>(path: T, format: VideoFormat, width: u32, height: u32, fps: u32) -> VideoWriter
+    where
+        T: AsRef<Path>,
+    {
+        let file = File::create(path).expect("Video file created");
+        let fps = fps.max(1);
+
+        let encoder = match format {
+            VideoFormat::Gif => {
+                let mut encoder = GifEncoder::new_with_speed(file, 10);
+                encoder
+                    .set_repeat(Repeat::Infinite)
+                    .expect("Gif repeat set");
+                VideoEncoder::Gif(encoder)
+            }
+            VideoFormat::Y4m => {
+                let mut writer = BufWriter::new(file);
+                writeln!(
+                    writer,
+                    "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C420jpeg",
+                    width, height, fps
+                )
+                .expect("Y4m header written");
+                VideoEncoder::Y4m(writer)
+            }
+        };
+
+        VideoWriter {
+            encoder,
+            width,
+            height,
+            fps,
+        }
+    }
+
+    pub fn write_frame(&mut self, frame: &RgbImage) {
+        assert_eq!(
+            (frame.width(), frame.height()),
+            (self.width, self.height),
+            "Video frame size changed"
+        );
+
+        match &mut self.encoder {
+            VideoEncoder::Gif(encoder) => {
+                let rgba = DynamicImage::ImageRgb8(frame.clone()).into_rgba8();
+                let delay = Delay::from_numer_denom_ms(1000, self.fps);
+                encoder
+                    .encode_frame(Frame::from_parts(rgba, 0, 0, delay))
+                    .expect("Gif frame written");
+            }
+            VideoEncoder::Y4m(writer) => {
+                writer.write_all(b"FRAME\n").expect("Y4m frame written");
+                writer
+                    .write_all(&rgb_to_yuv420(frame))
+                    .expect("Y4m frame written");
+                writer.flush().expect("Y4m frame flushed");
+            }
+        }
+    }
+}
+
+/// Planar Y, Cb, Cr bytes of the frame (BT.601, limited range as expected by y4m readers).
+/// Chroma is averaged over 2x2 blocks, odd sizes are rounded up.
+fn rgb_to_yuv420(frame: &RgbImage) -> Vec<u8> {
+    let (width, height) = frame.dimensions();
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    let luma_size = (width * height) as usize;
+    let chroma_size = (chroma_width * chroma_height) as usize;
+
+    let mut planes = vec![0u8; luma_size + chroma_size * 2];
+    let mut chroma = vec![(0.0f32, 0.0f32, 0u32); chroma_size];
+
+    for (x, y, pixel) in frame.enumerate_pixels() {
+        let [r, g, b] = pixel.0.map(|v| v as f32 / 255.0);
+
+        let luma = 16.0 + 65.481 * r + 128.553 * g + 24.966 * b;
+        let cb = 128.0 - 37.797 * r - 74.203 * g + 112.0 * b;
+        let cr = 128.0 + 112.0 * r - 93.786 * g - 18.214 * b;
+
+        planes[(y * width + x) as usize] = luma.round() as u8;
+        let block = &mut chroma[((y / 2) * chroma_width + x / 2) as usize];
+        block.0 += cb;
+        block.1 += cr;
+        block.2 += 1;
+    }
+
+    for (i, (cb, cr, count)) in chroma.into_iter().enumerate() {
+        planes[luma_size + i] = (cb / count as f32).round() as u8;
+        planes[luma_size + chroma_size + i] = (cr / count as f32).round() as u8;
+    }
+
+    planes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_path;
+    use image::{codecs::gif::GifDecoder, AnimationDecoder, Rgb};
+
+    fn create_frames() -> Vec<RgbImage> {
+        vec![
+            RgbImage::from_pixel(5, 3, Rgb([255, 255, 255])),
+            RgbImage::from_fn(5, 3, |x, _| Rgb([x as u8 * 60, 0, 0])),
+        ]
+    }
+
+    fn write_video(format: VideoFormat, name: &str) -> Vec<u8> {
+        let path = temp_path(name);
+        {
+            let mut writer = VideoWriter::new(&path, format, 5, 3, 30);
+            for frame in create_frames() {
+                writer.write_frame(&frame);
+            }
+        }
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn y4m_has_header_and_420_frames() {
+        let bytes = write_video(VideoFormat::Y4m, "video.y4m");
+
+        let header = b"YUV4MPEG2 W5 H3 F30:1 Ip A1:1 C420jpeg\n";
+        assert_eq!(&bytes[..header.len()], header);
+
+        // 5x3 luma and two 3x2 chroma planes
+        let frame_size = 5 * 3 + 3 * 2 * 2;
+        let frame = |i: usize| &bytes[header.len() + i * (6 + frame_size)..][..6 + frame_size];
+        assert_eq!(bytes.len(), header.len() + 2 * (6 + frame_size));
+
+        let first = frame(0);
+        assert_eq!(&first[..6], b"FRAME\n");
+        assert!(first[6..6 + 15].iter().all(|&luma| luma == 235));
+        assert!(first[6 + 15..].iter().all(|&chroma| chroma == 128));
+
+        let second = frame(1);
+        assert_eq!(&second[..6], b"FRAME\n");
+        // red raises Cr
+        assert!(second[6 + 15 + 6..].iter().all(|&cr| cr > 128));
+    }
+
+    #[test]
+    fn gif_has_all_frames() {
+        let bytes = write_video(VideoFormat::Gif, "video.gif");
+
+        let decoder = GifDecoder::new(bytes.as_slice()).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].buffer().dimensions(), (5, 3));
+    }
+}
@@ -1,5 +1,104 @@
+use crate::canvas::{
+    raw::{RawColorSpace, RawFormat},
+    tone_mapping::ToneMapping,
+    video::VideoFormat,
+};
 use envconfig::Envconfig;
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Where photons are processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightBackend {
+    /// Use wgpu if there is an adapter available, otherwise CPU.
+    Auto,
+    Gpu,
+    Cpu,
+}
+
+/// Optical model of the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CameraKind {
+    /// Camera obscura
+    Pinhole,
+    /// Thin lens with depth of field
+    Lens,
+}
+
+impl FromStr for CameraKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pinhole" => Ok(CameraKind::Pinhole),
+            "lens" => Ok(CameraKind::Lens),
+            _ => Err(format!("Unknown camera kind: {}", s)),
+        }
+    }
+}
+
+impl FromStr for LightBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(LightBackend::Auto),
+            "gpu" => Ok(LightBackend::Gpu),
+            "cpu" => Ok(LightBackend::Cpu),
+            _ => Err(format!("Unknown light backend: {}", s)),
+        }
+    }
+}
+
+/// How gravity between stars is calculated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GravitySolverKind {
+    /// Exact sum over all pairs, O(n²)
+    Direct,
+    /// Octree approximation, O(n log n)
+    BarnesHut,
+    /// Exact sum in a wgpu compute shader
+    Gpu,
+}
+
+impl FromStr for GravitySolverKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "direct" => Ok(GravitySolverKind::Direct),
+            "barnes-hut" | "barnes_hut" => Ok(GravitySolverKind::BarnesHut),
+            "gpu" => Ok(GravitySolverKind::Gpu),
+            _ => Err(format!("Unknown gravity solver: {}", s)),
+        }
+    }
+}
+
+/// Numerical method used to move stars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegratorKind {
+    Euler,
+    SemiImplicitEuler,
+    /// Kick-drift-kick leapfrog
+    Leapfrog,
+    /// 4th order Runge-Kutta
+    Rk4,
+}
+
+impl FromStr for IntegratorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "euler" => Ok(IntegratorKind::Euler),
+            "semi-implicit-euler" | "semi_implicit_euler" => Ok(IntegratorKind::SemiImplicitEuler),
+            "leapfrog" | "kdk" => Ok(IntegratorKind::Leapfrog),
+            "rk4" => Ok(IntegratorKind::Rk4),
+            _ => Err(format!("Unknown integrator: {}", s)),
+        }
+    }
+}
 
 #[derive(Envconfig)]
 pub struct Config {
@@ -7,21 +106,168 @@ pub struct Config {
     #[envconfig(from = "PHOTONS_SPAWN_RATE", default = "1000")]
     pub photons_spawn_rate: usize,
 
+    /// Seed of all random numbers in the simulation, picked randomly (and logged) if not set
+    #[envconfig(from = "SEED")]
+    pub seed: Option<u64>,
+
+    /// Derive stars surface temperature from their mass (main sequence), otherwise pick it randomly
+    #[envconfig(from = "TEMPERATURE_FROM_MASS", default = "true")]
+    pub temperature_from_mass: bool,
+
+    /// Stars surface temperature range in kelvins
+    #[envconfig(from = "MIN_STAR_TEMPERATURE", default = "2500")]
+    pub min_star_temperature: f32,
+
+    #[envconfig(from = "MAX_STAR_TEMPERATURE", default = "40000")]
+    pub max_star_temperature: f32,
+
     #[envconfig(from = "out_dir", default = "out")]
     pub out_dir: String,
 
+    /// Tone mapping operator: "linear", "reinhard", "aces" or "log"
+    #[envconfig(from = "TONE_MAPPING", default = "linear")]
+    pub tone_mapping: ToneMapping,
+
+    /// Exposure compensation in stops (EV), applied on top of auto exposure
+    #[envconfig(from = "EXPOSURE", default = "0.0")]
+    pub exposure: f32,
+
+    /// Pick exposure from the luminance histogram of the image
+    #[envconfig(from = "AUTO_EXPOSURE", default = "false")]
+    pub auto_exposure: bool,
+
+    /// Fraction of lit pixels which should be below white with auto exposure
+    #[envconfig(from = "AUTO_EXPOSURE_PERCENTILE", default = "0.99")]
+    pub auto_exposure_percentile: f32,
+
+    /// Exposed value mapped to white by "reinhard" and "log" tone mapping
+    #[envconfig(from = "TONE_MAPPING_WHITE", default = "4.0")]
+    pub tone_mapping_white: f32,
+
+    /// Also save raw float sensor data of each saved frame: "exr" or "tiff"
+    #[envconfig(from = "RAW_FORMAT")]
+    pub raw_format: Option<RawFormat>,
+
+    /// Write all frames into a single animation file in `out_dir`: "gif" or "y4m"
+    #[envconfig(from = "VIDEO_FORMAT")]
+    pub video_format: Option<VideoFormat>,
+
+    /// Add a frame to the video every n iterations
+    #[envconfig(from = "VIDEO_FRAME_INTERVAL", default = "1")]
+    pub video_frame_interval: usize,
+
+    /// Playback frame rate of the video
+    #[envconfig(from = "VIDEO_FPS", default = "30")]
+    pub video_fps: u32,
+
+    /// Channels of the raw sensor data: "xyz" or "linear-srgb"
+    #[envconfig(from = "RAW_COLOR_SPACE", default = "xyz")]
+    pub raw_color_space: RawColorSpace,
+
     /// amount of max iterations count
     #[envconfig(from = "PHOTONS_TTL", default = "10")]
     pub photons_ttl: usize,
 
+    /// Camera's optical model: "pinhole" or "lens"
+    #[envconfig(from = "CAMERA_KIND", default = "pinhole")]
+    pub camera_kind: CameraKind,
+
     #[envconfig(from = "CAMERA_HOLE_SIZE", default = "0.01")]
     pub camera_hole_size: f32,
 
+    /// Radius of the lens camera's aperture
+    #[envconfig(from = "CAMERA_APERTURE", default = "0.1")]
+    pub camera_aperture: f32,
+
+    /// Distance from the lens camera to the plane which is in focus
+    #[envconfig(from = "CAMERA_FOCUS_DISTANCE", default = "8.0")]
+    pub camera_focus_distance: f32,
+
     /// How fast pixel will fade out after each iteration
     #[envconfig(from = "FADE_OUT_SPEED", default = "0.9999")]
     pub fade_out_speed: f32,
+
+    /// Photons processing backend: "auto", "gpu" or "cpu"
+    #[envconfig(from = "LIGHT_BACKEND", default = "auto")]
+    pub light_backend: LightBackend,
+
+    /// Request the software (fallback) wgpu adapter instead of a real gpu
+    #[envconfig(from = "GPU_FALLBACK_ADAPTER", default = "false")]
+    pub gpu_fallback_adapter: bool,
+
+    /// Stars gravity solver: "direct", "barnes-hut" or "gpu"
+    #[envconfig(from = "GRAVITY_SOLVER", default = "direct")]
+    pub gravity_solver: GravitySolverKind,
+
+    /// Barnes-Hut opening angle, smaller is more accurate and slower
+    #[envconfig(from = "BARNES_HUT_THETA", default = "0.5")]
+    pub barnes_hut_theta: f32,
+
+    /// Plummer softening length in light years, limits acceleration of close stars
+    #[envconfig(from = "GRAVITY_SOFTENING", default = "0.001")]
+    pub gravity_softening: f32,
+
+    /// Merge stars which come closer than `MERGE_DISTANCE` to each other
+    #[envconfig(from = "STAR_MERGING", default = "false")]
+    pub star_merging: bool,
+
+    /// Collision distance of stars in light years
+    #[envconfig(from = "MERGE_DISTANCE", default = "0.0001")]
+    pub merge_distance: f32,
+
+    /// Stars movement integrator: "euler", "semi-implicit-euler", "leapfrog" or "rk4"
+    #[envconfig(from = "INTEGRATOR", default = "leapfrog")]
+    pub integrator: IntegratorKind,
+
+    /// Whether photons paths are bent by the gravity of stars
+    #[envconfig(from = "LIGHT_GRAVITY", default = "true")]
+    pub light_gravity: bool,
+
+    /// Stars lighter than this (in sun masses) don't bend photons, black holes always do. <br>
+    /// Deflection by a star is 4 * G * M / (c * c * b): at b = 0.01 light years a million sun masses
+    /// turn a photon by 6e-5 rad, under 1/50 of a pixel of the default camera (2 / 512 rad),
+    /// while every bending star costs one more term per photon per iteration.
+    /// Set to 0 to bend light around every object
+    #[envconfig(from = "LIGHT_GRAVITY_MIN_MASS", default = "1000000")]
+    pub light_gravity_min_mass: f32,
+
+    /// Never open a window, only save frames to `out_dir`
+    #[envconfig(from = "HEADLESS", default = "false")]
+    pub headless: bool,
+
+    /// Move camera with keyboard and mouse in the canvas window
+    #[envconfig(from = "REALTIME", default = "false")]
+    pub realtime: bool,
+
+    /// Distance camera moves per iteration while movement key is pressed
+    #[envconfig(from = "CAMERA_SPEED", default = "0.05")]
+    pub camera_speed: f32,
+}
+
+impl Config {
+    /// Check values which can't be expressed by their types.
+    pub fn validate(&self) -> Result<(), String> {
+        let temperatures = self.min_star_temperature..=self.max_star_temperature;
+        if !(self.min_star_temperature > 0.0
+            && self.max_star_temperature.is_finite()
+            && !temperatures.is_empty())
+        {
+            return Err(format!(
+                "MIN_STAR_TEMPERATURE and MAX_STAR_TEMPERATURE must satisfy 0 < min <= max, got {:?}",
+                temperatures
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 lazy_static! {
-    pub static ref CONFIG: Config = Config::init_from_env().unwrap();
+    pub static ref CONFIG: Config = {
+        let config = Config::init_from_env().unwrap();
+        if let Err(e) = config.validate() {
+            panic!("Invalid configuration: {}", e);
+        }
+        config
+    };
 }
@@ -5,3 +5,6 @@ pub mod object;
 pub mod photons;
 pub mod physics_constants;
 pub mod world;
+
+#[cfg(test)]
+mod test_utils;
@@ -1,34 +1,185 @@
-use glam::Vec2;
-use light::{camera::Camera, canvas::Canvas, config::CONFIG, world::World};
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Parser, Subcommand};
+use light::{
+    camera::controller::CameraController,
+    canvas::{video::VideoWriter, Canvas},
+    config::CONFIG,
+    world::{scene::Scene, World},
+};
+
+mod bench;
+
+/// Physically based light simulation.
+///
+/// Everything not covered by flags is configured with environment variables (or `.env` file).
+#[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Never open a window (same as the `render` subcommand)
+    #[arg(long)]
+    headless: bool,
+
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render frames into the output directory without a window
+    Render(RenderArgs),
+    /// Show frames in a window while rendering
+    Preview(RenderArgs),
+    /// Measure speed of the simulation steps
+    Bench(bench::BenchArgs),
+}
+
+#[derive(Args)]
+struct RenderArgs {
+    /// RON scene file, a single galaxy if not set
+    #[arg(short, long)]
+    scene: Option<PathBuf>,
+
+    /// Image width, overrides the scene
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    width: Option<u32>,
+
+    /// Image height, overrides the scene
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    height: Option<u32>,
+
+    /// Exit after this many iterations, run forever if not set
+    #[arg(short = 'n', long)]
+    iterations: Option<usize>,
+
+    /// Output directory (`out_dir`)
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+
+    /// Random seed (`SEED`)
+    #[arg(long)]
+    seed: Option<u64>,
+}
 
-#[show_image::main]
 fn main() {
     dotenv::dotenv().ok();
     env_logger::init();
 
-    std::fs::create_dir_all(&CONFIG.out_dir).unwrap();
+    let cli = Cli::parse();
 
-    let width: u32 = 512;
-    let height: u32 = 512;
+    let (args, with_window) = match cli.command {
+        Some(Command::Render(args)) => (args, false),
+        Some(Command::Preview(args)) => (args, true),
+        Some(Command::Bench(args)) => {
+            if !bench::bench_world(&args) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        None => (cli.render, !cli.headless),
+    };
 
-    let mut world = World::new();
-    let camera = Camera {
-        hole_radius: CONFIG.camera_hole_size,
-        focal_length: 1.0,
-        sensor_size: Vec2::new(2.0, 2.0),
+    // batch jobs should see a failed scene, same as `bench`
+    let world = match create_world(args.scene.as_ref(), args.seed, false) {
+        Some(world) => world,
+        None => std::process::exit(1),
     };
-    let mut canvas = Canvas::new(width, height);
+
+    if with_window && !CONFIG.headless {
+        show_image::run_context(move || run(true, &args, world));
+    } else {
+        run(false, &args, world);
+    }
+}
+
+/// World from the scene file, or the default one.
+/// `seed` and `software_adapter` override `SEED` and `GPU_FALLBACK_ADAPTER`.
+fn create_world(
+    scene: Option<&PathBuf>,
+    seed: Option<u64>,
+    software_adapter: bool,
+) -> Option<World> {
+    let scene = match scene {
+        Some(path) => match Scene::load(path) {
+            Ok(scene) => scene,
+            Err(e) => {
+                log::error!("{} ({})", e, path.display());
+                return None;
+            }
+        },
+        None => Scene::default(),
+    };
+
+    Some(World::with_seed(
+        &scene,
+        seed.or(CONFIG.seed).unwrap_or_else(rand::random),
+        CONFIG.light_backend,
+        software_adapter || CONFIG.gpu_fallback_adapter,
+    ))
+}
+
+fn run(with_window: bool, args: &RenderArgs, mut world: World) {
+    let out_dir = args
+        .out
+        .as_deref()
+        .unwrap_or_else(|| Path::new(&CONFIG.out_dir));
+    std::fs::create_dir_all(out_dir).unwrap();
+
+    let width = args.width.unwrap_or(world.render_settings().width);
+    let height = args.height.unwrap_or(world.render_settings().height);
+
+    let mut camera = world
+        .camera_settings()
+        .create_camera(world.get_center_of_mass());
+    let mut canvas = if with_window {
+        Canvas::with_window(width, height)
+    } else {
+        Canvas::new(width, height)
+    };
+
+    let mut controller = match canvas.window_events() {
+        Some(events) if CONFIG.realtime => Some(CameraController::new(
+            events.expect("Window events channel created"),
+        )),
+        _ => None,
+    };
+
+    let mut video = CONFIG.video_format.map(|format| {
+        VideoWriter::new(
+            out_dir.join(format!("video.{}", format.extension())),
+            format,
+            width,
+            height,
+            CONFIG.video_fps,
+        )
+    });
 
     let mut itt = 0;
-    loop {
+    while args.iterations.is_none_or(|iterations| itt < iterations) {
         let start_time = std::time::Instant::now();
-        world.update_light(camera, &mut canvas);
+
+        if let Some(controller) = &mut controller {
+            if controller.update(camera.as_mut()) {
+                canvas.clear();
+            }
+        }
+
+        world.update_light(camera.as_ref(), &mut canvas);
         world.update_movement();
 
         canvas.update_fading();
 
         if itt % 10 == 0 {
-            canvas.save(format!("{}/{:0>8}-frame.png", CONFIG.out_dir, itt));
+            save_frame(&canvas, out_dir, itt);
+        }
+
+        if let Some(video) = &mut video {
+            if itt % CONFIG.video_frame_interval.max(1) == 0 {
+                video.write_frame(&canvas.generate_rgb());
+            }
         }
 
         if let Err(e) = canvas.show() {
@@ -39,4 +190,21 @@ fn main() {
         println!("Iteration {}; Time: {:?}", itt, start_time.elapsed());
         itt += 1;
     }
+
+    // last frame, unless it was just saved
+    if itt > 0 && (itt - 1) % 10 != 0 {
+        save_frame(&canvas, out_dir, itt - 1);
+    }
+}
+
+fn save_frame(canvas: &Canvas, out_dir: &Path, itt: usize) {
+    canvas.save(out_dir.join(format!("{:0>8}-frame.png", itt)));
+
+    if let Some(raw_format) = CONFIG.raw_format {
+        canvas.save_raw(
+            out_dir.join(format!("{:0>8}-frame.{}", itt, raw_format.extension())),
+            raw_format,
+            CONFIG.raw_color_space,
+        );
+    }
 }
@@ -0,0 +1,195 @@
+use std::f32::consts::PI;
+
+use glam::{Mat3, Vec3};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// χ² critical values with p = 0.001 for 3 and 5 degrees of freedom.
+const DIPOLE_CRITICAL_VALUE: f32 = 16.266;
+const QUADRUPOLE_CRITICAL_VALUE: f32 = 20.515;
+
+/// Angular distribution of photons emitted by an object.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum EmissionProfile {
+    /// Same intensity in every direction
+    #[default]
+    Isotropic,
+    /// Uniformly inside of a cone around `axis` (e.g. jets or a spotlight)
+    Cone { axis: Vec3, half_angle: f32 },
+    /// Thin disk with normal `axis` emitting to both sides with the linear limb darkening law
+    /// I(μ) = 1 - coefficient * (1 - μ), where μ is cosine between direction and the normal.
+    /// The disk is seen foreshortened, so photons per solid angle are I(μ) * μ
+    LimbDarkenedDisk { axis: Vec3, coefficient: f32 },
+}
+
+impl EmissionProfile {
+    /// Pick a direction (unit vector) of a single emitted photon.
+    pub fn sample_direction<R: Rng>(&self, rng: &mut R) -> Vec3 {
+        match *self {
+            EmissionProfile::Isotropic => {
+                sample_direction_around(Vec3::Z, rng.gen_range(-1.0..=1.0), rng)
+            }
+            EmissionProfile::Cone { axis, half_angle } => {
+                let min_cos = half_angle.clamp(0.0, PI).cos();
+                let cos_theta = rng.gen_range(min_cos..=1.0);
+                sample_direction_around(axis, cos_theta, rng)
+            }
+            EmissionProfile::LimbDarkenedDisk { axis, coefficient } => {
+                let coefficient = coefficient.clamp(0.0, 1.0);
+
+                // μ = sqrt(u) has the foreshortened (Lambertian) density 2μ,
+                // then reject by relative intensity (max is 1 at μ = 1)
+                let mu = loop {
+                    let mu = rng.gen::<f32>().sqrt();
+                    if rng.gen::<f32>() <= 1.0 - coefficient * (1.0 - mu) {
+                        break mu;
+                    }
+                };
+                let cos_theta = if rng.gen() { mu } else { -mu };
+
+                sample_direction_around(axis, cos_theta, rng)
+            }
+        }
+    }
+}
+
+/// Direction with the given cosine of angle to `axis` and uniformly random azimuth.
+fn sample_direction_around<R: Rng>(axis: Vec3, cos_theta: f32, rng: &mut R) -> Vec3 {
+    let basis = get_orthonormal_basis(axis.normalize());
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = rng.gen_range(0.0..2.0 * PI);
+
+    basis * Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+/// Rotation which maps Z axis to `normal` (Duff et al. 2017, "Building an Orthonormal Basis, Revisited").
+fn get_orthonormal_basis(normal: Vec3) -> Mat3 {
+    let sign = 1.0_f32.copysign(normal.z);
+    let a = -1.0 / (sign + normal.z);
+    let b = normal.x * normal.y * a;
+
+    let tangent = Vec3::new(
+        1.0 + sign * normal.x * normal.x * a,
+        sign * b,
+        -sign * normal.x,
+    );
+    let bitangent = Vec3::new(b, sign + normal.y * normal.y * a, -normal.y);
+
+    Mat3::from_cols(tangent, bitangent, normal)
+}
+
+/// Statistics of a set of directions which are χ² distributed if the directions are isotropic.
+#[derive(Debug, Clone, Copy)]
+pub struct IsotropyStatistic {
+    /// Rayleigh test, 3N|mean|², χ² with 3 degrees of freedom (detects preferred direction)
+    pub dipole: f32,
+    /// Bingham test, 15N/2 * |mean(d dᵀ) - I/3|², χ² with 5 degrees of freedom
+    /// (detects preferred axis or plane, e.g. oversampled poles)
+    pub quadrupole: f32,
+}
+
+impl IsotropyStatistic {
+    pub fn new(directions: &[Vec3]) -> IsotropyStatistic {
+        let n = directions.len().max(1) as f32;
+
+        let mean = directions.iter().fold(Vec3::ZERO, |sum, d| sum + *d) / n;
+        let scatter = directions.iter().fold(Mat3::ZERO, |sum, d| {
+            sum + Mat3::from_cols(*d * d.x, *d * d.y, *d * d.z)
+        }) * (1.0 / n);
+        let deviation = scatter - Mat3::IDENTITY * (1.0 / 3.0);
+        let deviation_squared: f32 = deviation
+            .to_cols_array()
+            .iter()
+            .map(|value| value * value)
+            .sum();
+
+        IsotropyStatistic {
+            dipole: 3.0 * n * mean.length_squared(),
+            quadrupole: 7.5 * n * deviation_squared,
+        }
+    }
+
+    /// Whether isotropy can not be rejected at 0.1% significance level.
+    pub fn is_isotropic(&self) -> bool {
+        self.dipole < DIPOLE_CRITICAL_VALUE && self.quadrupole < QUADRUPOLE_CRITICAL_VALUE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    const SAMPLES: usize = 100_000;
+
+    fn get_statistic(mut sample: impl FnMut(&mut ChaCha8Rng) -> Vec3) -> IsotropyStatistic {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let directions: Vec<Vec3> = (0..SAMPLES).map(|_| sample(&mut rng)).collect();
+        IsotropyStatistic::new(&directions)
+    }
+
+    #[test]
+    fn isotropic_is_isotropic() {
+        let statistic = get_statistic(|rng| EmissionProfile::Isotropic.sample_direction(rng));
+        assert!(statistic.is_isotropic(), "{:?}", statistic);
+    }
+
+    #[test]
+    fn directed_profiles_are_not_isotropic() {
+        let profiles = [
+            EmissionProfile::Cone {
+                axis: Vec3::Z,
+                half_angle: 0.5,
+            },
+            // symmetric, so only the quadrupole statistic sees it
+            EmissionProfile::LimbDarkenedDisk {
+                axis: Vec3::Z,
+                coefficient: 0.6,
+            },
+        ];
+
+        for profile in profiles {
+            let statistic = get_statistic(|rng| profile.sample_direction(rng));
+            assert!(!statistic.is_isotropic(), "{:?}: {:?}", profile, statistic);
+        }
+    }
+
+    /// Uniform spherical angles oversample the poles.
+    #[test]
+    fn uniform_angles_are_not_isotropic() {
+        let statistic = get_statistic(|rng| {
+            let theta = (rng.gen::<f32>() - 0.5) * 2.0 * PI;
+            let phi = (rng.gen::<f32>() - 0.5) * 2.0 * PI;
+            Vec3::new(theta.cos() * phi.cos(), phi.sin(), theta.sin() * phi.cos())
+        });
+        assert!(!statistic.is_isotropic(), "{:?}", statistic);
+    }
+
+    /// Mean μ of the density μ * (1 - c * (1 - μ)) on 0..1 is (4 - c) / (6 - 2c),
+    /// without foreshortening it would be (3 - c) / (6 - 3c).
+    #[test]
+    fn limb_darkened_disk_is_foreshortened() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        for coefficient in [0.0, 0.6, 1.0] {
+            let disk = EmissionProfile::LimbDarkenedDisk {
+                axis: Vec3::Y,
+                coefficient,
+            };
+            let mean_mu = (0..SAMPLES)
+                .map(|_| disk.sample_direction(&mut rng).y.abs())
+                .sum::<f32>()
+                / SAMPLES as f32;
+
+            let expected = (4.0 - coefficient) / (6.0 - 2.0 * coefficient);
+            assert!(
+                (mean_mu - expected).abs() < 0.005,
+                "coefficient {}: mean μ {}, expected {}",
+                coefficient,
+                mean_mu,
+                expected
+            );
+        }
+    }
+}
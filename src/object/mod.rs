@@ -1,37 +1,110 @@
 use crate::{
     config::CONFIG,
-    photons::{wavelength::WaveLength, Photon},
+    photons::{spectrum::Spectrum, Photon},
+    physics_constants::get_photon_sphere_radius,
 };
+use bytemuck::{Pod, Zeroable};
 use glam::Vec3;
-use std::f32::consts::PI;
+use rand::Rng;
+
+use self::emission::EmissionProfile;
+
+pub mod emission;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ObjectKind {
+    /// Emits photons
+    #[default]
+    Star,
+    /// Emits nothing and absorbs every photon which crosses its photon sphere
+    BlackHole,
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct Object {
+    pub kind: ObjectKind,
     pub pos: Vec3,
     pub vel: Vec3,
     pub mass: f32,
-    pub photons_wavelength: WaveLength,
+    /// wavelengths of emitted photons
+    pub spectrum: Spectrum,
+    /// directions of emitted photons
+    pub emission: EmissionProfile,
 
     /// how much photons are emitted per iteration
     pub luminosity: f32,
 }
 
 impl Object {
+    pub fn black_hole(pos: Vec3, mass: f32) -> Object {
+        Object {
+            kind: ObjectKind::BlackHole,
+            pos,
+            mass,
+            luminosity: 0.0,
+            ..Default::default()
+        }
+    }
+
     pub fn get_photons_per_frame(&self) -> usize {
-        (self.luminosity * (CONFIG.photons_spawn_rate as f32)) as usize
+        match self.kind {
+            ObjectKind::Star => (self.luminosity * (CONFIG.photons_spawn_rate as f32)) as usize,
+            ObjectKind::BlackHole => 0,
+        }
+    }
+
+    /// Photons whose (already bent) path crosses this sphere are absorbed, zero for stars.
+    /// Inside of the photon sphere there are no orbits, so they fall under the horizon.
+    pub fn get_absorption_radius(&self) -> f32 {
+        match self.kind {
+            ObjectKind::Star => 0.0,
+            ObjectKind::BlackHole => get_photon_sphere_radius(self.mass),
+        }
     }
-    pub fn spawn_photons(&self, photons: &mut Vec<Photon>) {
+
+    /// Whether light bending by this object is worth computing, black holes always bend.
+    pub fn is_gravity_well(&self, min_mass: f32) -> bool {
+        match self.kind {
+            ObjectKind::Star => self.mass >= min_mass,
+            ObjectKind::BlackHole => true,
+        }
+    }
+
+    /// Emit photons of one iteration, `ttl` - amount of iterations they will live.
+    pub fn spawn_photons<R: Rng>(&self, rng: &mut R, ttl: usize, photons: &mut Vec<Photon>) {
         let spawn_count = self.get_photons_per_frame();
 
         for _ in 0..spawn_count {
-            let theta = (rand::random::<f32>() - 0.5) * 2.0 * PI;
-            let phi = (rand::random::<f32>() - 0.5) * 2.0 * PI;
+            let photon = Photon::new(
+                self.spectrum.sample(rng),
+                self.pos,
+                self.emission.sample_direction(rng),
+                ttl as u32,
+            );
 
-            let direction = Vec3::new(theta.cos() * phi.cos(), phi.sin(), theta.sin() * phi.cos());
+            photons.push(photon);
+        }
+    }
+}
 
-            let photon = Photon::new(self.photons_wavelength, self.pos, direction);
+/// Point mass which bends photons paths.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct GravityWell {
+    pub pos: [f32; 3],
+    pub mass: f32,
+    /// photons whose step crosses this sphere are absorbed, zero for stars
+    pub absorption_radius: f32,
+    pub _pad: [f32; 3],
+}
 
-            photons.push(photon);
+impl From<&Object> for GravityWell {
+    fn from(object: &Object) -> Self {
+        GravityWell {
+            pos: object.pos.to_array(),
+            mass: object.mass,
+            absorption_radius: object.get_absorption_radius(),
+            _pad: [0.0; 3],
         }
     }
 }
@@ -39,10 +112,12 @@ impl Object {
 impl Default for Object {
     fn default() -> Self {
         Self {
+            kind: ObjectKind::Star,
             pos: Vec3::ZERO,
             vel: Vec3::ZERO,
             mass: 1.0,
-            photons_wavelength: WaveLength::default(),
+            spectrum: Spectrum::default(),
+            emission: EmissionProfile::default(),
             luminosity: 1.0,
         }
     }
@@ -0,0 +1,180 @@
+//! CIE 1931 2° standard observer and XYZ to sRGB conversion.
+
+use glam::{Mat3, Vec3};
+
+/// First wavelength of `COLOR_MATCHING_FUNCTIONS` in nanometers.
+pub const CMF_START: f32 = 380.0;
+
+/// Step between rows of `COLOR_MATCHING_FUNCTIONS` in nanometers.
+pub const CMF_STEP: f32 = 5.0;
+
+/// CIE 1931 2° color matching functions x̄, ȳ, z̄ from 380 nm to 780 nm.
+///
+/// Values are copied as published in CIE 15:2004 (table T.4, 5 nm steps),
+/// trailing zeros are kept to keep the table aligned.
+#[rustfmt::skip]
+#[allow(clippy::excessive_precision)]
+pub const COLOR_MATCHING_FUNCTIONS: [[f32; 3]; 81] = [
+    [0.001368, 0.000039, 0.006450], // 380
+    [0.002236, 0.000064, 0.010550],
+    [0.004243, 0.000120, 0.020050],
+    [0.007650, 0.000217, 0.036210],
+    [0.014310, 0.000396, 0.067850], // 400
+    [0.023190, 0.000640, 0.110200],
+    [0.043510, 0.001210, 0.207400],
+    [0.077630, 0.002180, 0.371300],
+    [0.134380, 0.004000, 0.645600], // 420
+    [0.214770, 0.007300, 1.039050],
+    [0.283900, 0.011600, 1.385600],
+    [0.328500, 0.016840, 1.622960],
+    [0.348280, 0.023000, 1.747060], // 440
+    [0.348060, 0.029800, 1.782600],
+    [0.336200, 0.038000, 1.772110],
+    [0.318700, 0.048000, 1.744100],
+    [0.290800, 0.060000, 1.669200], // 460
+    [0.251100, 0.073900, 1.528100],
+    [0.195360, 0.090980, 1.287640],
+    [0.142100, 0.112600, 1.041900],
+    [0.095640, 0.139020, 0.812950], // 480
+    [0.057950, 0.169300, 0.616200],
+    [0.032010, 0.208020, 0.465180],
+    [0.014700, 0.258600, 0.353300],
+    [0.004900, 0.323000, 0.272000], // 500
+    [0.002400, 0.407300, 0.212300],
+    [0.009300, 0.503000, 0.158200],
+    [0.029100, 0.608200, 0.111700],
+    [0.063270, 0.710000, 0.078250], // 520
+    [0.109600, 0.793200, 0.057250],
+    [0.165500, 0.862000, 0.042160],
+    [0.225750, 0.914850, 0.029840],
+    [0.290400, 0.954000, 0.020300], // 540
+    [0.359700, 0.980300, 0.013400],
+    [0.433450, 0.994950, 0.008750],
+    [0.512050, 1.000000, 0.005750],
+    [0.594500, 0.995000, 0.003900], // 560
+    [0.678400, 0.978600, 0.002750],
+    [0.762100, 0.952000, 0.002100],
+    [0.842500, 0.915400, 0.001800],
+    [0.916300, 0.870000, 0.001650], // 580
+    [0.978600, 0.816300, 0.001400],
+    [1.026300, 0.757000, 0.001100],
+    [1.056700, 0.694900, 0.001000],
+    [1.062200, 0.631000, 0.000800], // 600
+    [1.045600, 0.566800, 0.000600],
+    [1.002600, 0.503000, 0.000340],
+    [0.938400, 0.441200, 0.000240],
+    [0.854450, 0.381000, 0.000190], // 620
+    [0.751400, 0.321000, 0.000100],
+    [0.642400, 0.265000, 0.000050],
+    [0.541900, 0.217000, 0.000030],
+    [0.447900, 0.175000, 0.000020], // 640
+    [0.360800, 0.138200, 0.000010],
+    [0.283500, 0.107000, 0.000000],
+    [0.218700, 0.081600, 0.000000],
+    [0.164900, 0.061000, 0.000000], // 660
+    [0.121200, 0.044580, 0.000000],
+    [0.087400, 0.032000, 0.000000],
+    [0.063600, 0.023200, 0.000000],
+    [0.046770, 0.017000, 0.000000], // 680
+    [0.032900, 0.011920, 0.000000],
+    [0.022700, 0.008210, 0.000000],
+    [0.015840, 0.005723, 0.000000],
+    [0.011359, 0.004102, 0.000000], // 700
+    [0.008111, 0.002929, 0.000000],
+    [0.005790, 0.002091, 0.000000],
+    [0.004109, 0.001484, 0.000000],
+    [0.002899, 0.001047, 0.000000], // 720
+    [0.002049, 0.000740, 0.000000],
+    [0.001440, 0.000520, 0.000000],
+    [0.001000, 0.000361, 0.000000],
+    [0.000690, 0.000249, 0.000000], // 740
+    [0.000476, 0.000172, 0.000000],
+    [0.000332, 0.000120, 0.000000],
+    [0.000235, 0.000085, 0.000000],
+    [0.000166, 0.000060, 0.000000], // 760
+    [0.000117, 0.000042, 0.000000],
+    [0.000083, 0.000030, 0.000000],
+    [0.000059, 0.000021, 0.000000],
+    [0.000042, 0.000015, 0.000000], // 780
+];
+
+/// XYZ to linear sRGB (D65 white point), columns of the matrix.
+const XYZ_TO_LINEAR_SRGB: Mat3 = Mat3::from_cols_array(&[
+    3.240454, -0.969266, 0.05564343, //
+    -1.537139, 1.876011, -0.2040259, //
+    -0.4985314, 0.04155601, 1.057225,
+]);
+
+/// CIE XYZ tristimulus of a single photon with wavelength `wavelength` in nanometers
+/// (color matching functions linearly interpolated, zero outside of the visible range).
+pub fn wavelength_to_xyz(wavelength: f32) -> Vec3 {
+    let position = (wavelength - CMF_START) / CMF_STEP;
+    if !(0.0..=(COLOR_MATCHING_FUNCTIONS.len() - 1) as f32).contains(&position) {
+        return Vec3::ZERO;
+    }
+
+    let index = (position as usize).min(COLOR_MATCHING_FUNCTIONS.len() - 2);
+    let t = position - index as f32;
+
+    Vec3::from(COLOR_MATCHING_FUNCTIONS[index])
+        .lerp(Vec3::from(COLOR_MATCHING_FUNCTIONS[index + 1]), t)
+}
+
+pub fn xyz_to_linear_srgb(xyz: Vec3) -> Vec3 {
+    XYZ_TO_LINEAR_SRGB * xyz
+}
+
+/// sRGB transfer function (gamma encoding) of a linear value in 0..1 range.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Gamma encoded sRGB color in 0..1 range, out of gamut colors are clipped.
+pub fn xyz_to_srgb(xyz: Vec3) -> Vec3 {
+    encode_srgb(xyz_to_linear_srgb(xyz))
+}
+
+/// Clip linear sRGB color to 0..1 range and gamma encode it.
+pub fn encode_srgb(rgb: Vec3) -> Vec3 {
+    let rgb = rgb.clamp(Vec3::ZERO, Vec3::ONE);
+    Vec3::new(
+        linear_to_srgb(rgb.x),
+        linear_to_srgb(rgb.y),
+        linear_to_srgb(rgb.z),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wavelength_matches_table_rows() {
+        assert_eq!(
+            wavelength_to_xyz(380.0),
+            Vec3::new(0.001368, 0.000039, 0.00645)
+        );
+        assert_eq!(wavelength_to_xyz(555.0), Vec3::new(0.51205, 1.0, 0.00575));
+        assert_eq!(wavelength_to_xyz(600.0), Vec3::new(1.0622, 0.631, 0.0008));
+        assert_eq!(wavelength_to_xyz(780.0), Vec3::new(0.000042, 0.000015, 0.0));
+
+        // halfway between 600 and 605 nm
+        let expected = (Vec3::new(1.0622, 0.631, 0.0008) + Vec3::new(1.0456, 0.5668, 0.0006)) * 0.5;
+        assert!(wavelength_to_xyz(602.5).abs_diff_eq(expected, 1e-6));
+
+        assert_eq!(wavelength_to_xyz(379.0), Vec3::ZERO);
+        assert_eq!(wavelength_to_xyz(781.0), Vec3::ZERO);
+    }
+
+    #[test]
+    fn d65_white_point_is_white() {
+        let d65 = Vec3::new(0.95047, 1.0, 1.08883);
+
+        assert!(xyz_to_linear_srgb(d65).abs_diff_eq(Vec3::ONE, 1e-3));
+        assert!(xyz_to_srgb(d65).abs_diff_eq(Vec3::ONE, 1e-3));
+    }
+}
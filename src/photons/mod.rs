@@ -1,7 +1,13 @@
 use self::wavelength::WaveLength;
+use crate::{
+    object::GravityWell,
+    physics_constants::{get_light_gravity_acceleration, TIME_SPEED},
+};
 use bytemuck::{Pod, Zeroable};
 use glam::Vec3;
 
+pub mod cie;
+pub mod spectrum;
 pub mod wavelength;
 
 #[repr(C)]
@@ -10,19 +16,29 @@ pub struct Photon {
     pub pos: [f32; 4],
     pub dir: [f32; 4],
     pub wavelength: WaveLength,
-    pub _pad: [f32; 3],
+    /// amount of iterations photon already lived
+    pub age: u32,
+    /// amount of iterations after which photon is dead
+    pub ttl: u32,
+    pub _pad: u32,
 }
 
 impl Photon {
-    pub fn new(wavelength: wavelength::WaveLength, pos: Vec3, dir: Vec3) -> Photon {
+    pub fn new(wavelength: wavelength::WaveLength, pos: Vec3, dir: Vec3, ttl: u32) -> Photon {
         Photon {
             wavelength,
             pos: [pos.x, pos.y, pos.z, 0.0],
             dir: [dir.x, dir.y, dir.z, 0.0],
-            _pad: [0.0, 0.0, 0.0],
+            age: 0,
+            ttl,
+            _pad: 0,
         }
     }
 
+    pub fn is_alive(&self) -> bool {
+        self.age < self.ttl
+    }
+
     pub fn get_wavelength(&self) -> wavelength::WaveLength {
         self.wavelength
     }
@@ -39,17 +55,112 @@ impl Photon {
         self.wavelength = wavelength;
     }
 
-    pub fn process(&self) -> Self {
+    /// Bend photon direction towards gravity wells and move it one step forward,
+    /// must match `process` from `shader.wgsl`
+    pub fn process(&self, gravity_wells: &[GravityWell]) -> Self {
+        if !self.is_alive() {
+            return *self;
+        }
+
+        let pos = self.get_position();
+        let mut dir = self.get_direction();
+
+        for well in gravity_wells {
+            let delta = Vec3::from(well.pos) - pos;
+            let r_2 = delta.length_squared();
+            if r_2 > 0.0 {
+                dir +=
+                    delta.normalize() * get_light_gravity_acceleration(well.mass, r_2) * TIME_SPEED;
+            }
+        }
+
+        let dir = dir.normalize();
+        let movement = dir * TIME_SPEED;
+
+        let absorbed = gravity_wells.iter().any(|well| {
+            well.absorption_radius > 0.0
+                && get_segment_distance_squared(pos, movement, Vec3::from(well.pos))
+                    < well.absorption_radius * well.absorption_radius
+        });
+
+        let pos = pos + movement;
+
         Photon {
-            pos: [
-                self.pos[0] + self.dir[0],
-                self.pos[1] + self.dir[1],
-                self.pos[2] + self.dir[2],
-                0.0,
-            ],
-            dir: self.dir,
+            pos: [pos.x, pos.y, pos.z, self.pos[3]],
+            dir: [dir.x, dir.y, dir.z, self.dir[3]],
             wavelength: self.wavelength,
-            _pad: [0.0, 0.0, 0.0],
+            age: if absorbed { self.ttl } else { self.age + 1 },
+            ttl: self.ttl,
+            _pad: 0,
         }
     }
 }
+
+/// Squared distance between `point` and the segment from `start` to `start + delta`.
+fn get_segment_distance_squared(start: Vec3, delta: Vec3, point: Vec3) -> f32 {
+    let t = ((point - start).dot(delta) / delta.length_squared()).clamp(0.0, 1.0);
+    (start + delta * t - point).length_squared()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        object::Object,
+        physics_constants::{get_photon_sphere_radius, GRAVITY_CONSTANT_UNIT},
+    };
+
+    #[test]
+    fn black_hole_absorbs_photons_crossing_photon_sphere() {
+        let black_hole = Object::black_hole(Vec3::ZERO, 1e12);
+        let wells = [GravityWell::from(&black_hole)];
+        assert_eq!(
+            wells[0].absorption_radius,
+            get_photon_sphere_radius(black_hole.mass)
+        );
+
+        let falling = Photon::new(WaveLength::WHITE, Vec3::new(-0.6, 0.0, 0.0), Vec3::X, 10);
+        assert!(!falling.process(&wells).is_alive());
+
+        let passing = Photon::new(WaveLength::WHITE, Vec3::new(-0.5, 2.0, 0.0), Vec3::X, 10);
+        let passing = passing.process(&wells);
+        assert!(passing.is_alive());
+        assert!(
+            passing.get_direction().y < 0.0,
+            "bent towards the black hole"
+        );
+    }
+
+    #[test]
+    fn deflection_by_point_mass_matches_general_relativity() {
+        let mass = 1.6e11;
+        let impact = 10.0;
+        let well = GravityWell {
+            pos: [0.0; 3],
+            mass,
+            absorption_radius: 0.0,
+            _pad: [0.0; 3],
+        };
+
+        let mut photon = Photon::new(
+            WaveLength::WHITE,
+            Vec3::new(-1000.0, impact, 0.0),
+            Vec3::X,
+            2000,
+        );
+        while photon.is_alive() {
+            photon = photon.process(&[well]);
+        }
+
+        // alpha = 4 * G * M / (c^2 * b)
+        let expected = 4.0 * GRAVITY_CONSTANT_UNIT * mass / impact;
+        let dir = photon.get_direction();
+        let deflection = (-dir.y).atan2(dir.x);
+        assert!(
+            (deflection - expected).abs() < 0.02 * expected,
+            "deflection {} expected {}",
+            deflection,
+            expected
+        );
+    }
+}
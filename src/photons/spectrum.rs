@@ -0,0 +1,149 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{cie::CMF_START, wavelength::WaveLength};
+use crate::physics_constants::{PLANCK_C2, SUN_TEMPERATURE};
+
+/// Longest wavelength which can be seen, in nanometers.
+const VISIBLE_END: f32 = 780.0;
+
+/// Photon radiance is maximal at λ = c2 / (x * T), where x is the solution of x = 4 * (1 - exp(-x)).
+const PHOTON_PEAK_X: f64 = 3.920690395;
+
+/// Rejection sampling gives up after this many tries and returns the brightest wavelength.
+const MAX_SAMPLE_ATTEMPTS: usize = 1000;
+
+/// Distribution of wavelengths of photons emitted by an object.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Spectrum {
+    /// All photons have the same wavelength
+    Monochromatic(WaveLength),
+    /// Black body with the given surface temperature in kelvins
+    BlackBody(f32),
+}
+
+impl Spectrum {
+    /// Pick a wavelength of a single emitted photon.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> WaveLength {
+        match *self {
+            Spectrum::Monochromatic(wavelength) => wavelength,
+            Spectrum::BlackBody(temperature) => sample_black_body(temperature, rng),
+        }
+    }
+}
+
+impl Default for Spectrum {
+    fn default() -> Self {
+        Spectrum::BlackBody(SUN_TEMPERATURE)
+    }
+}
+
+/// Photon spectral radiance of a black body from Planck's law, without constant factors.  <br>
+/// Photons count is energy divided by photon energy h * c / λ, so N(λ) ~ λ^-4 / (exp(c2 / (λ * T)) - 1)
+fn get_photon_radiance(wavelength: f32, temperature: f32) -> f32 {
+    let wavelength = wavelength as f64;
+    let exponent = PLANCK_C2 / (wavelength * temperature as f64);
+    (1.0 / (wavelength.powi(4) * exponent.exp_m1())) as f32
+}
+
+/// Rejection sampling of visible wavelengths from Planck's law.  <br>
+/// Temperatures which are not finite and positive have no spectrum, they give `WaveLength::WHITE`.
+fn sample_black_body<R: Rng>(temperature: f32, rng: &mut R) -> WaveLength {
+    if !(temperature.is_finite() && temperature > 0.0) {
+        return WaveLength::WHITE;
+    }
+
+    // radiance has a single peak, so its maximum in the visible range is at the clamped peak
+    let peak = (PLANCK_C2 / (PHOTON_PEAK_X * temperature as f64)) as f32;
+    let peak = peak.clamp(CMF_START, VISIBLE_END);
+    let max = get_photon_radiance(peak, temperature);
+
+    for _ in 0..MAX_SAMPLE_ATTEMPTS {
+        let wavelength = rng.gen_range(CMF_START..VISIBLE_END);
+        if rng.gen::<f32>() * max <= get_photon_radiance(wavelength, temperature) {
+            return WaveLength(wavelength);
+        }
+    }
+
+    WaveLength(peak)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    /// Wien's displacement constant in nm * K.
+    const WIEN_B: f32 = 2.897772e6;
+
+    fn sample_wavelengths(temperature: f32, count: usize) -> Vec<f32> {
+        let mut rng = ChaCha8Rng::seed_from_u64(temperature as u64);
+        (0..count)
+            .map(|_| Spectrum::BlackBody(temperature).sample(&mut rng).0)
+            .collect()
+    }
+
+    #[test]
+    fn energy_peaks_at_wien_wavelength() {
+        const BIN_WIDTH: f32 = 20.0;
+        let bins = ((VISIBLE_END - CMF_START) / BIN_WIDTH) as usize;
+
+        for temperature in [4500.0, 5000.0, 6000.0, 7000.0] {
+            // photons are counted, energy of each of them is ~ 1 / λ
+            let mut energy = vec![0.0; bins];
+            for wavelength in sample_wavelengths(temperature, 200_000) {
+                let bin = (((wavelength - CMF_START) / BIN_WIDTH) as usize).min(bins - 1);
+                energy[bin] += 1.0 / wavelength;
+            }
+
+            let peak_bin = (0..bins)
+                .max_by(|a, b| energy[*a].total_cmp(&energy[*b]))
+                .unwrap();
+            let peak = CMF_START + (peak_bin as f32 + 0.5) * BIN_WIDTH;
+            let expected = WIEN_B / temperature;
+            assert!(
+                (peak - expected).abs() <= 2.0 * BIN_WIDTH,
+                "{} K: peak {} nm, expected {} nm",
+                temperature,
+                peak,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn hotter_bodies_are_bluer() {
+        let means: Vec<f32> = [2500.0, 4000.0, 6000.0, 10_000.0, 40_000.0]
+            .into_iter()
+            .map(|temperature| {
+                let wavelengths = sample_wavelengths(temperature, 20_000);
+                wavelengths.iter().sum::<f32>() / wavelengths.len() as f32
+            })
+            .collect();
+
+        assert!(
+            means.windows(2).all(|pair| pair[1] < pair[0]),
+            "mean wavelengths {:?}",
+            means
+        );
+    }
+
+    #[test]
+    fn invalid_temperature_does_not_hang() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        for temperature in [f32::NAN, f32::INFINITY, 0.0, -100.0] {
+            assert_eq!(
+                sample_black_body(temperature, &mut rng),
+                WaveLength::WHITE,
+                "{}",
+                temperature
+            );
+        }
+
+        // almost nothing is emitted in the visible range, but sampling still ends
+        let wavelength = sample_black_body(1.0, &mut rng);
+        assert!((CMF_START..=VISIBLE_END).contains(&wavelength.0));
+    }
+}
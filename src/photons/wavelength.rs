@@ -1,8 +1,11 @@
+use super::cie::{wavelength_to_xyz, xyz_to_srgb};
 use bytemuck::{Pod, Zeroable};
-use colors_transform::{Color, Hsl};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Pod, Zeroable)]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Pod, Zeroable, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct WaveLength(pub f32);
 
 impl WaveLength {
@@ -11,6 +14,11 @@ impl WaveLength {
     pub fn new(value: f32) -> WaveLength {
         WaveLength(value)
     }
+
+    /// CIE XYZ tristimulus of a photon with this wavelength.
+    pub fn to_xyz(self) -> Vec3 {
+        wavelength_to_xyz(self.0)
+    }
 }
 
 impl Default for WaveLength {
@@ -19,24 +27,17 @@ impl Default for WaveLength {
     }
 }
 
-impl Into<(f32, f32, f32)> for WaveLength {
-    /// wavelength to rgb
-    fn into(self) -> (f32, f32, f32) {
-        if (self.0 < 380.) || (self.0 > 750.) {
-            return (0., 0., 0.);
-        }
-
-        let hue = (650. - self.0 as f32) * 240. / (650. - 475.);
-        let rgb = Hsl::from(hue, 100., 50.0).to_rgb();
-
-        rgb.as_tuple()
+impl From<WaveLength> for (f32, f32, f32) {
+    /// wavelength to gamma encoded sRGB, using CIE 1931 color matching functions
+    fn from(wavelength: WaveLength) -> Self {
+        xyz_to_srgb(wavelength.to_xyz()).into()
     }
 }
 
-impl Into<[u8; 3]> for WaveLength {
+impl From<WaveLength> for [u8; 3] {
     /// wavelength to rgb
-    fn into(self) -> [u8; 3] {
-        let v: (f32, f32, f32) = self.into();
+    fn from(wavelength: WaveLength) -> Self {
+        let v: (f32, f32, f32) = wavelength.into();
         [
             (v.0 * 255.0) as u8,
             (v.1 * 255.0) as u8,
@@ -3,6 +3,9 @@ pub const C_SI: f64 = 299792458.0;
 
 pub const TIME_SPEED: f32 = 1.0;
 
+/// speed of photons in game units (photon travels `DIST_SI` per `TIME_SI`)
+pub const C_UNIT: f32 = 1.0;
+
 /// time between each frame in seconds
 pub const TIME_SI: f64 = 365. * 24. * 60. * 60.;
 const TIME_SI_2: f64 = TIME_SI * TIME_SI;
@@ -17,6 +20,12 @@ pub const MASS_SI_2: f64 = MASS_SI * MASS_SI;
 
 pub const GRAVITY_CONSTANT_SI: f64 = 6.67408e-11;
 
+/// surface temperature of sun in kelvins
+pub const SUN_TEMPERATURE: f32 = 5772.0;
+
+/// second radiation constant h * c / k in nm * K
+pub const PLANCK_C2: f64 = 1.438777e7;
+
 /// Gravitational constant in game units
 ///
 /// F_SI = G * m1 * MASS_SI * m2 * MASS_SI / (r * DIST_SI * r * DIST_SI)  <br>
@@ -38,3 +47,27 @@ pub const GRAVITY_CONSTANT_UNIT: f32 =
 pub fn get_gravity_acceleration(other_mass: f32, r_2: f32) -> f32 {
     other_mass / r_2 * GRAVITY_CONSTANT_UNIT
 }
+
+/// calculate the acceleration of a photon towards a body of mass `other_mass`  <br>
+/// General relativity gives twice the deflection of a newtonian particle moving with speed of light.
+pub fn get_light_gravity_acceleration(other_mass: f32, r_2: f32) -> f32 {
+    2.0 * get_gravity_acceleration(other_mass, r_2)
+}
+
+/// radius of the event horizon of a black hole of mass `mass`  <br>
+/// r_s = 2 * G * m / (c * c)
+pub fn get_schwarzschild_radius(mass: f32) -> f32 {
+    2.0 * GRAVITY_CONSTANT_UNIT * mass / (C_UNIT * C_UNIT)
+}
+
+/// radius of the sphere where photons orbit a black hole of mass `mass`  <br>
+/// r_ph = 1.5 * r_s
+pub fn get_photon_sphere_radius(mass: f32) -> f32 {
+    1.5 * get_schwarzschild_radius(mass)
+}
+
+/// surface temperature in kelvins of a main sequence star of mass `mass`  <br>
+/// L ~ m^3.5 and R ~ m^0.8, so from Stefan–Boltzmann law T ~ (L / R^2)^(1/4) ~ m^0.475
+pub fn get_main_sequence_temperature(mass: f32) -> f32 {
+    SUN_TEMPERATURE * mass.powf(0.475)
+}
@@ -0,0 +1,35 @@
+//! Helpers shared by unit tests of different modules.
+
+use futures::executor::block_on;
+use std::{path::PathBuf, sync::Arc};
+
+use crate::world::GpuContext;
+
+/// Set to skip tests which need a wgpu adapter, on machines without even a software one.
+const SKIP_GPU_TESTS: &str = "SKIP_GPU_TESTS";
+
+/// Device on the software (fallback) adapter, so results don't depend on the gpu.  <br>
+/// Panics if there is no adapter, unless `SKIP_GPU_TESTS` is set, then the skip is reported and None returned.
+pub fn gpu_context() -> Option<Arc<GpuContext>> {
+    if std::env::var_os(SKIP_GPU_TESTS).is_some() {
+        // test harness names threads after the tests
+        let thread = std::thread::current();
+        let test = thread.name().unwrap_or("gpu test");
+        eprintln!("SKIPPED {}: {} is set", test, SKIP_GPU_TESTS);
+        return None;
+    }
+
+    let context = block_on(GpuContext::with_fallback_adapter(true)).unwrap_or_else(|| {
+        panic!(
+            "No wgpu fallback adapter on this machine, set {} to skip gpu tests",
+            SKIP_GPU_TESTS
+        )
+    });
+
+    Some(Arc::new(context))
+}
+
+/// Path of a file in the system temp directory, unique for this test process.
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("light-{}-{}", std::process::id(), name))
+}
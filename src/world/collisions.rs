@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use glam::{IVec3, Vec3};
+
+use crate::object::{Object, ObjectKind};
+
+/// Merge every group of bodies closer than `distance` to each other into a single body,
+/// conserving mass, momentum and center of mass. Returns the amount of removed bodies.
+///
+/// The merged body is a black hole if any of the group is one,
+/// spectrum and emission are taken from the heaviest body and luminosities are summed.
+pub fn merge_close_bodies(bodies: &mut Vec<Object>, distance: f32) -> usize {
+    if distance <= 0.0 || bodies.len() < 2 {
+        return 0;
+    }
+
+    // spatial hash with cells of `distance` size, so only neighbor cells have to be checked
+    let get_cell = |pos: Vec3| (pos / distance).floor().as_ivec3();
+    let mut cells: HashMap<IVec3, Vec<usize>> = HashMap::new();
+    for (i, body) in bodies.iter().enumerate() {
+        cells.entry(get_cell(body.pos)).or_default().push(i);
+    }
+
+    let mut groups = DisjointSet::new(bodies.len());
+    let distance_2 = distance * distance;
+    for (i, body) in bodies.iter().enumerate() {
+        let cell = get_cell(body.pos);
+
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    let neighbors = match cells.get(&(cell + IVec3::new(x, y, z))) {
+                        Some(neighbors) => neighbors,
+                        None => continue,
+                    };
+
+                    for &j in neighbors.iter().filter(|&&j| j > i) {
+                        if body.pos.distance_squared(bodies[j].pos) < distance_2 {
+                            groups.union(i, j);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut merged: HashMap<usize, Object> = HashMap::new();
+    let mut heaviest: HashMap<usize, f32> = HashMap::new();
+    let mut result = Vec::with_capacity(bodies.len());
+    let mut roots = Vec::with_capacity(bodies.len());
+
+    for (i, body) in bodies.iter().enumerate() {
+        let root = groups.find(i);
+        roots.push(root);
+
+        let group = merged.entry(root).or_insert(Object {
+            mass: 0.0,
+            luminosity: 0.0,
+            pos: Vec3::ZERO,
+            vel: Vec3::ZERO,
+            ..*body
+        });
+
+        // position and velocity are mass weighted sums until the group is complete
+        group.pos += body.pos * body.mass;
+        group.vel += body.vel * body.mass;
+        group.mass += body.mass;
+        group.luminosity += body.luminosity;
+
+        if body.kind == ObjectKind::BlackHole {
+            group.kind = ObjectKind::BlackHole;
+        }
+
+        let heaviest_mass = heaviest.entry(root).or_insert(f32::NEG_INFINITY);
+        if body.mass > *heaviest_mass {
+            *heaviest_mass = body.mass;
+            group.spectrum = body.spectrum;
+            group.emission = body.emission;
+        }
+    }
+
+    // keep the order of bodies, every group takes the place of its first body
+    for (i, root) in roots.into_iter().enumerate() {
+        if let Some(mut group) = merged.remove(&root) {
+            if group.mass > 0.0 {
+                group.pos /= group.mass;
+                group.vel /= group.mass;
+            } else {
+                group.pos = bodies[i].pos;
+                group.vel = bodies[i].vel;
+            }
+
+            if group.kind == ObjectKind::BlackHole {
+                group.luminosity = 0.0;
+            }
+
+            result.push(group);
+        }
+    }
+
+    let removed = bodies.len() - result.len();
+    *bodies = result;
+
+    removed
+}
+
+/// Union-find over body indices.
+struct DisjointSet {
+    parents: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(size: usize) -> DisjointSet {
+        DisjointSet {
+            parents: (0..size).collect(),
+        }
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+
+        index
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let a = self.find(a);
+        let b = self.find(b);
+        if a != b {
+            self.parents[b.max(a)] = a.min(b);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_momentum(bodies: &[Object]) -> Vec3 {
+        bodies
+            .iter()
+            .fold(Vec3::ZERO, |sum, body| sum + body.vel * body.mass)
+    }
+
+    #[test]
+    fn merge_conserves_mass_and_momentum() {
+        let far = Object {
+            pos: Vec3::new(10.0, 0.0, 0.0),
+            vel: Vec3::new(0.0, 1.0, 0.0),
+            mass: 3.0,
+            ..Default::default()
+        };
+        let mut bodies = vec![
+            Object {
+                pos: Vec3::new(0.0, 0.0, 0.0),
+                vel: Vec3::new(1.0, 0.0, 0.0),
+                mass: 1.0,
+                ..Default::default()
+            },
+            far,
+            Object {
+                pos: Vec3::new(0.05, 0.0, 0.0),
+                vel: Vec3::new(-1.0, 2.0, 0.0),
+                mass: 2.0,
+                ..Default::default()
+            },
+            // close to the second body only, still the same group
+            Object {
+                pos: Vec3::new(0.12, 0.0, 0.0),
+                vel: Vec3::new(0.0, 0.0, 4.0),
+                mass: 4.0,
+                ..Object::black_hole(Vec3::ZERO, 0.0)
+            },
+        ];
+
+        let mass: f32 = bodies.iter().map(|body| body.mass).sum();
+        let momentum = get_momentum(&bodies);
+
+        assert_eq!(merge_close_bodies(&mut bodies, 0.1), 2);
+        assert_eq!(bodies.len(), 2);
+
+        let merged = bodies[0];
+        assert_eq!(merged.kind, ObjectKind::BlackHole);
+        assert_eq!(merged.mass, 7.0);
+        assert!(merged
+            .pos
+            .abs_diff_eq(Vec3::new((0.05 * 2.0 + 0.12 * 4.0) / 7.0, 0.0, 0.0), 1e-6));
+        assert_eq!(bodies[1].pos, far.pos);
+
+        assert_eq!(bodies.iter().map(|body| body.mass).sum::<f32>(), mass);
+        assert!(get_momentum(&bodies).abs_diff_eq(momentum, 1e-5));
+    }
+}
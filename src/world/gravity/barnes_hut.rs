@@ -0,0 +1,330 @@
+use glam::Vec3;
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+use super::{get_point_acceleration, GravitySolver};
+use crate::object::Object;
+
+/// Cells are not split deeper, bodies which end up in the same deepest cell are merged into it
+/// (it only happens for (almost) coincident bodies) and summed one by one.
+const MAX_DEPTH: u32 = 32;
+
+/// Approximates far groups of bodies by their center of mass, O(n log n).
+///
+/// A cell of size `s` at distance `d` is approximated if `s / d < theta`,
+/// `theta = 0` gives the exact result (but slower than `DirectSolver`).
+#[derive(Debug, Clone)]
+pub struct BarnesHutSolver {
+    pub theta: f32,
+    /// Plummer softening length
+    pub softening: f32,
+    /// Octree nodes, reused between iterations to avoid allocations
+    nodes: Vec<Node>,
+    /// Bodies of each merged leaf node
+    merged: HashMap<usize, Vec<usize>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    center: Vec3,
+    half_size: f32,
+    mass: f32,
+    /// Mass weighted sum of positions while building, center of mass after `finish`
+    mass_center: Vec3,
+    /// Index of the first of 8 children, None for leaves
+    children: Option<usize>,
+    /// The only body of a leaf, None for empty leaves and merged ones
+    body: Option<usize>,
+    /// Amount of bodies inside of the cell
+    count: usize,
+}
+
+impl Node {
+    fn new(center: Vec3, half_size: f32) -> Node {
+        Node {
+            center,
+            half_size,
+            mass: 0.0,
+            mass_center: Vec3::ZERO,
+            children: None,
+            body: None,
+            count: 0,
+        }
+    }
+
+    fn contains(&self, pos: Vec3) -> bool {
+        (pos - self.center).abs().max_element() <= self.half_size
+    }
+
+    fn get_octant(&self, pos: Vec3) -> usize {
+        (pos.x >= self.center.x) as usize
+            | ((pos.y >= self.center.y) as usize) << 1
+            | ((pos.z >= self.center.z) as usize) << 2
+    }
+}
+
+impl BarnesHutSolver {
+    pub fn new(theta: f32, softening: f32) -> BarnesHutSolver {
+        BarnesHutSolver {
+            theta,
+            softening,
+            nodes: vec![],
+            merged: HashMap::new(),
+        }
+    }
+
+    fn build(&mut self, bodies: &[Object]) {
+        self.nodes.clear();
+        self.merged.clear();
+
+        let (min, max) = bodies.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), body| (min.min(body.pos), max.max(body.pos)),
+        );
+        let half_size = ((max - min).max_element() * 0.5).max(f32::EPSILON);
+        self.nodes.push(Node::new((min + max) * 0.5, half_size));
+
+        for (index, body) in bodies.iter().enumerate() {
+            self.insert(0, index, bodies, 0);
+            let root = &mut self.nodes[0];
+            root.mass += body.mass;
+            root.mass_center += body.pos * body.mass;
+        }
+
+        for node in self.nodes.iter_mut() {
+            if node.mass > 0.0 {
+                node.mass_center /= node.mass;
+            }
+        }
+    }
+
+    /// Put a body into the subtree of `node_index`, node itself is already updated by the caller.
+    fn insert(&mut self, node_index: usize, index: usize, bodies: &[Object], depth: u32) {
+        let node = self.nodes[node_index];
+        self.nodes[node_index].count += 1;
+
+        if node.children.is_none() {
+            if node.count == 0 {
+                self.nodes[node_index].body = Some(index);
+                return;
+            }
+
+            if depth >= MAX_DEPTH {
+                let merged = self.merged.entry(node_index).or_default();
+                if let Some(previous) = self.nodes[node_index].body.take() {
+                    merged.push(previous);
+                }
+                merged.push(index);
+                return;
+            }
+
+            self.subdivide(node_index);
+
+            // the body is still counted in this node, only pass it to a child
+            if let Some(previous) = node.body {
+                self.nodes[node_index].body = None;
+                self.insert_into_child(node_index, previous, bodies, depth);
+            }
+        }
+
+        self.insert_into_child(node_index, index, bodies, depth);
+    }
+
+    fn insert_into_child(
+        &mut self,
+        node_index: usize,
+        index: usize,
+        bodies: &[Object],
+        depth: u32,
+    ) {
+        let node = self.nodes[node_index];
+        let body = &bodies[index];
+        let child_index = node.children.expect("Node is subdivided") + node.get_octant(body.pos);
+
+        let child = &mut self.nodes[child_index];
+        child.mass += body.mass;
+        child.mass_center += body.pos * body.mass;
+
+        self.insert(child_index, index, bodies, depth + 1);
+    }
+
+    fn subdivide(&mut self, node_index: usize) {
+        let node = self.nodes[node_index];
+        let half_size = node.half_size * 0.5;
+        let first_child = self.nodes.len();
+
+        for octant in 0..8 {
+            let offset = Vec3::new(
+                if octant & 1 != 0 {
+                    half_size
+                } else {
+                    -half_size
+                },
+                if octant & 2 != 0 {
+                    half_size
+                } else {
+                    -half_size
+                },
+                if octant & 4 != 0 {
+                    half_size
+                } else {
+                    -half_size
+                },
+            );
+            self.nodes.push(Node::new(node.center + offset, half_size));
+        }
+
+        self.nodes[node_index].children = Some(first_child);
+    }
+
+    fn get_acceleration(&self, index: usize, bodies: &[Object]) -> Vec3 {
+        let body = &bodies[index];
+        let mut a = Vec3::ZERO;
+        let mut stack = vec![0];
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if node.count == 0 {
+                continue;
+            }
+
+            match node.children {
+                Some(first_child) => {
+                    let distance = node.mass_center.distance(body.pos);
+                    let is_far = node.half_size * 2.0 < self.theta * distance;
+
+                    if is_far && !node.contains(body.pos) {
+                        a += get_point_acceleration(
+                            body.pos,
+                            node.mass_center,
+                            node.mass,
+                            self.softening,
+                        );
+                    } else {
+                        stack.extend(first_child..first_child + 8);
+                    }
+                }
+                None => match node.body {
+                    Some(other) if other == index => {}
+                    Some(_) => {
+                        a += get_point_acceleration(
+                            body.pos,
+                            node.mass_center,
+                            node.mass,
+                            self.softening,
+                        )
+                    }
+                    None => {
+                        // merged bodies, subtracting the body itself from the cell's center of mass
+                        // would leave a rounding error instead of zero distance
+                        for &other in self.merged.get(&node_index).into_iter().flatten() {
+                            if other != index {
+                                a += get_point_acceleration(
+                                    body.pos,
+                                    bodies[other].pos,
+                                    bodies[other].mass,
+                                    self.softening,
+                                );
+                            }
+                        }
+                    }
+                },
+            }
+        }
+
+        a
+    }
+}
+
+impl GravitySolver for BarnesHutSolver {
+    fn get_accelerations(&mut self, bodies: &[Object]) -> Vec<Vec3> {
+        if bodies.is_empty() {
+            return vec![];
+        }
+
+        self.build(bodies);
+
+        let solver = &*self;
+        (0..bodies.len())
+            .into_par_iter()
+            .map(|index| solver.get_acceleration(index, bodies))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::gravity::DirectSolver;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn zero_theta_matches_direct_solver() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut bodies: Vec<Object> = (0..64)
+            .map(|_| Object {
+                pos: Vec3::new(rng.gen(), rng.gen(), rng.gen()),
+                mass: rng.gen_range(1.0..10.0),
+                ..Default::default()
+            })
+            .collect();
+
+        // coincident bodies end up in the same MAX_DEPTH leaf
+        for i in 0..3 {
+            bodies.push(Object {
+                mass: 5.0,
+                ..bodies[i * 10]
+            });
+        }
+        bodies.push(Object {
+            mass: 2.0,
+            ..bodies[0]
+        });
+
+        for softening in [0.0, 0.01] {
+            let exact = DirectSolver::new(softening).get_accelerations(&bodies);
+            let approximate = BarnesHutSolver::new(0.0, softening).get_accelerations(&bodies);
+
+            for (i, (exact, approximate)) in exact.iter().zip(approximate.iter()).enumerate() {
+                assert!(
+                    (*exact - *approximate).length() <= exact.length() * 1e-4,
+                    "body {}: {} != {}",
+                    i,
+                    exact,
+                    approximate
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn default_theta_is_close_to_direct_solver() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let bodies: Vec<Object> = (0..2000)
+            .map(|_| Object {
+                pos: Vec3::new(rng.gen(), rng.gen(), rng.gen()),
+                mass: rng.gen_range(1.0..10.0),
+                ..Default::default()
+            })
+            .collect();
+
+        let softening = 0.001;
+        let exact = DirectSolver::new(softening).get_accelerations(&bodies);
+        let approximate = BarnesHutSolver::new(0.5, softening).get_accelerations(&bodies);
+
+        let mut errors: Vec<f32> = exact
+            .iter()
+            .zip(approximate.iter())
+            .map(|(exact, approximate)| (*exact - *approximate).length() / exact.length())
+            .collect();
+        errors.sort_by(|a, b| a.total_cmp(b));
+        let median = errors[errors.len() / 2];
+        let max = errors[errors.len() - 1];
+
+        // far cells are really approximated, but only a little
+        assert!(median > 1e-5, "median relative error {}", median);
+        assert!(median < 0.01, "median relative error {}", median);
+        assert!(max < 0.1, "max relative error {}", max);
+    }
+}
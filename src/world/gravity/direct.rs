@@ -0,0 +1,74 @@
+use glam::{Vec3, Vec3A};
+use rayon::prelude::*;
+
+use super::GravitySolver;
+use crate::{object::Object, physics_constants::get_gravity_acceleration};
+
+/// Exact O(n²) sum over all pairs of bodies.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DirectSolver {
+    /// Plummer softening length
+    pub softening: f32,
+    /// Split bodies between rayon threads
+    pub parallel: bool,
+}
+
+impl DirectSolver {
+    pub fn new(softening: f32) -> DirectSolver {
+        DirectSolver {
+            softening,
+            parallel: true,
+        }
+    }
+
+    /// Single threaded solver, e.g. to compare with the parallel one.
+    pub fn serial(softening: f32) -> DirectSolver {
+        DirectSolver {
+            softening,
+            parallel: false,
+        }
+    }
+
+    /// Acceleration of the body `index` caused by all other bodies.
+    fn get_body_acceleration(&self, index: usize, masses: &[(Vec3A, f32)]) -> Vec3 {
+        let (pos, _) = masses[index];
+        let softening_2 = self.softening * self.softening;
+        let mut a = Vec3A::ZERO;
+
+        for (j, &(other_pos, other_mass)) in masses.iter().enumerate() {
+            if index == j {
+                continue;
+            }
+
+            // same as `get_point_acceleration`, but with SIMD friendly `Vec3A`
+            let delta = other_pos - pos;
+            let r_2 = delta.length_squared() + softening_2;
+            if r_2 > 0.0 {
+                a += delta * (get_gravity_acceleration(other_mass, r_2) / r_2.sqrt());
+            }
+        }
+
+        a.into()
+    }
+}
+
+impl GravitySolver for DirectSolver {
+    fn get_accelerations(&mut self, bodies: &[Object]) -> Vec<Vec3> {
+        let masses: Vec<(Vec3A, f32)> = bodies
+            .iter()
+            .map(|body| (body.pos.into(), body.mass))
+            .collect();
+
+        let solver = &*self;
+        if solver.parallel {
+            (0..masses.len())
+                .into_par_iter()
+                .map(|i| solver.get_body_acceleration(i, &masses))
+                .collect()
+        } else {
+            (0..masses.len())
+                .map(|i| solver.get_body_acceleration(i, &masses))
+                .collect()
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use super::GravitySolver;
+use crate::{
+    object::Object, physics_constants::GRAVITY_CONSTANT_UNIT, world::light_processing::GpuContext,
+};
+use bytemuck::{Pod, Zeroable};
+use futures::executor::block_on;
+use glam::Vec3;
+use std::{borrow::Cow, sync::Arc};
+
+/// Bodies per workgroup, same as `@workgroup_size` in `nbody.wgsl`.
+const WORKGROUP_SIZE: u32 = 64;
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable, Default)]
+struct Params {
+    amount: u32,
+    gravity_constant: f32,
+    softening_2: f32,
+    _pad: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable, Default)]
+struct GpuBody {
+    pos: [f32; 3],
+    mass: f32,
+}
+
+/// Bodies and accelerations buffers, recreated when there are more bodies than they can hold.
+struct BodiesBuffers {
+    capacity: usize,
+
+    bodies_buffer: wgpu::Buffer,
+    accelerations_buffer: wgpu::Buffer,
+    staging_buffer: wgpu::Buffer,
+
+    bind_group: wgpu::BindGroup,
+}
+
+/// Exact all pairs gravity in a wgpu compute shader, on the same device as photons.
+pub struct GpuGravitySolver {
+    context: Arc<GpuContext>,
+    /// Plummer softening length
+    pub softening: f32,
+
+    compute_pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    buffers: Option<BodiesBuffers>,
+}
+
+impl BodiesBuffers {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        params_buffer: &wgpu::Buffer,
+        capacity: usize,
+    ) -> Self {
+        let bodies_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Bodies Buffer"),
+            size: (capacity * std::mem::size_of::<GpuBody>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let accelerations_size = Self::accelerations_bytes_for(capacity);
+        let accelerations_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Accelerations Buffer"),
+            size: accelerations_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: accelerations_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: bodies_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: accelerations_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        BodiesBuffers {
+            capacity,
+            bodies_buffer,
+            accelerations_buffer,
+            staging_buffer,
+            bind_group,
+        }
+    }
+
+    fn accelerations_bytes_for(capacity: usize) -> wgpu::BufferAddress {
+        (capacity * std::mem::size_of::<[f32; 4]>()) as wgpu::BufferAddress
+    }
+}
+
+impl GpuGravitySolver {
+    pub fn new(context: Arc<GpuContext>, softening: f32) -> Self {
+        let device = context.device();
+
+        let cs_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("nbody.wgsl"))),
+        });
+
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: None,
+            module: &cs_module,
+            entry_point: "main",
+        });
+
+        let bind_group_layout = compute_pipeline.get_bind_group_layout(0);
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Params Buffer"),
+            size: std::mem::size_of::<Params>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        GpuGravitySolver {
+            context,
+            softening,
+            compute_pipeline,
+            bind_group_layout,
+            params_buffer,
+            buffers: None,
+        }
+    }
+
+    fn update_buffers(&mut self, amount: usize) {
+        if let Some(buffers) = &self.buffers {
+            if buffers.capacity >= amount {
+                return;
+            }
+        }
+
+        self.buffers = Some(BodiesBuffers::new(
+            self.context.device(),
+            &self.bind_group_layout,
+            &self.params_buffer,
+            amount,
+        ));
+    }
+
+    async fn compute(&mut self, bodies: &[Object]) -> Vec<Vec3> {
+        self.update_buffers(bodies.len());
+
+        let buffers = match &self.buffers {
+            Some(buffers) => buffers,
+            None => return vec![],
+        };
+        let device = self.context.device();
+        let queue = self.context.queue();
+
+        let gpu_bodies: Vec<GpuBody> = bodies
+            .iter()
+            .map(|body| GpuBody {
+                pos: body.pos.to_array(),
+                mass: body.mass,
+            })
+            .collect();
+        queue.write_buffer(&buffers.bodies_buffer, 0, bytemuck::cast_slice(&gpu_bodies));
+
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::cast_slice(&[Params {
+                amount: bodies.len() as u32,
+                gravity_constant: GRAVITY_CONSTANT_UNIT,
+                softening_2: self.softening * self.softening,
+                _pad: 0,
+            }]),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let workgroups = (bodies.len() as u32).div_ceil(WORKGROUP_SIZE);
+
+            let mut compute_pass =
+                encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+            compute_pass.set_pipeline(&self.compute_pipeline);
+            compute_pass.set_bind_group(0, &buffers.bind_group, &[]);
+            compute_pass.insert_debug_marker("compute gravity");
+            compute_pass.dispatch_workgroups(workgroups, 1, 1);
+        }
+
+        let size = BodiesBuffers::accelerations_bytes_for(bodies.len());
+        encoder.copy_buffer_to_buffer(
+            &buffers.accelerations_buffer,
+            0,
+            &buffers.staging_buffer,
+            0,
+            size,
+        );
+
+        queue.submit(Some(encoder.finish()));
+
+        let buffer_slice = buffers.staging_buffer.slice(..size);
+
+        let (sender, receiver) = futures_intrusive::channel::shared::oneshot_channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |v| sender.send(v).unwrap());
+
+        device.poll(wgpu::Maintain::Wait);
+
+        if let Some(Ok(())) = receiver.receive().await {
+            let data = buffer_slice.get_mapped_range();
+            let result: &[[f32; 4]] = bytemuck::cast_slice(&data);
+            let accelerations = result.iter().map(|a| Vec3::new(a[0], a[1], a[2])).collect();
+
+            drop(data);
+            buffers.staging_buffer.unmap();
+
+            accelerations
+        } else {
+            panic!("Failed to map buffer");
+        }
+    }
+}
+
+impl GravitySolver for GpuGravitySolver {
+    fn get_accelerations(&mut self, bodies: &[Object]) -> Vec<Vec3> {
+        if bodies.is_empty() {
+            return vec![];
+        }
+
+        block_on(self.compute(bodies))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils, world::gravity::DirectSolver};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn matches_direct_solver() {
+        let Some(context) = test_utils::gpu_context() else {
+            return;
+        };
+
+        // not a multiple of the workgroup size
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let bodies: Vec<Object> = (0..300)
+            .map(|_| Object {
+                pos: Vec3::new(rng.gen(), rng.gen(), rng.gen()),
+                mass: rng.gen_range(1.0..1000.0),
+                ..Default::default()
+            })
+            .collect();
+
+        let softening = 0.001;
+        let exact = DirectSolver::new(softening).get_accelerations(&bodies);
+        let mut solver = GpuGravitySolver::new(context, softening);
+
+        // second run reuses the buffers
+        for _ in 0..2 {
+            let accelerations = solver.get_accelerations(&bodies);
+            assert_eq!(accelerations.len(), bodies.len());
+
+            for (i, (exact, gpu)) in exact.iter().zip(accelerations.iter()).enumerate() {
+                assert!(
+                    (*exact - *gpu).length() <= exact.length() * 1e-4,
+                    "body {}: {} != {}",
+                    i,
+                    exact,
+                    gpu
+                );
+            }
+        }
+    }
+}
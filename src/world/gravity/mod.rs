@@ -0,0 +1,118 @@
+use glam::Vec3;
+use std::sync::Arc;
+
+use super::light_processing::GpuContext;
+use crate::{
+    config::GravitySolverKind,
+    object::Object,
+    physics_constants::{get_gravity_acceleration, GRAVITY_CONSTANT_UNIT},
+};
+
+pub use self::{barnes_hut::BarnesHutSolver, direct::DirectSolver, gpu::GpuGravitySolver};
+
+mod barnes_hut;
+mod direct;
+mod gpu;
+
+/// Computes gravitational acceleration of every body caused by all other bodies.
+pub trait GravitySolver: Send {
+    /// Acceleration of each body, in the same order as `bodies`.
+    fn get_accelerations(&mut self, bodies: &[Object]) -> Vec<Vec3>;
+}
+
+/// `gpu_context` is only used by the gpu solver, which falls back to the direct one without it.
+pub fn create_gravity_solver(
+    kind: GravitySolverKind,
+    theta: f32,
+    softening: f32,
+    gpu_context: Option<&Arc<GpuContext>>,
+) -> Box<dyn GravitySolver> {
+    match (kind, gpu_context) {
+        (GravitySolverKind::Direct, _) => Box::new(DirectSolver::new(softening)),
+        (GravitySolverKind::BarnesHut, _) => Box::new(BarnesHutSolver::new(theta, softening)),
+        (GravitySolverKind::Gpu, Some(context)) => {
+            Box::new(GpuGravitySolver::new(context.clone(), softening))
+        }
+        (GravitySolverKind::Gpu, None) => {
+            log::warn!("No wgpu adapter found, falling back to direct gravity solver");
+            Box::new(DirectSolver::new(softening))
+        }
+    }
+}
+
+/// Total kinetic and (softened) potential energy of the bodies in game units,
+/// it would stay constant with an exact integration.
+pub fn get_total_energy(bodies: &[Object], softening: f32) -> f64 {
+    let softening_2 = softening as f64 * softening as f64;
+    let mut energy = 0.0;
+
+    for (i, body) in bodies.iter().enumerate() {
+        energy += 0.5 * body.mass as f64 * body.vel.length_squared() as f64;
+
+        for other in bodies[i + 1..].iter() {
+            let r_2 = body.pos.distance_squared(other.pos) as f64 + softening_2;
+            if r_2 > 0.0 {
+                energy -= GRAVITY_CONSTANT_UNIT as f64 * body.mass as f64 * other.mass as f64
+                    / r_2.sqrt();
+            }
+        }
+    }
+
+    energy
+}
+
+/// Acceleration of a body at `pos` towards a point mass with Plummer softening:
+/// a = G * m * delta / (r² + ε²)^(3/2), so close encounters can't produce huge accelerations.
+pub fn get_point_acceleration(pos: Vec3, other_pos: Vec3, other_mass: f32, softening: f32) -> Vec3 {
+    let delta = other_pos - pos;
+    let r_2 = delta.length_squared() + softening * softening;
+
+    // coincident bodies without softening, direction is undefined
+    if r_2 <= 0.0 {
+        return Vec3::ZERO;
+    }
+
+    delta * (get_gravity_acceleration(other_mass, r_2) / r_2.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coincident_bodies_have_finite_accelerations() {
+        let body = Object {
+            pos: Vec3::new(1.0, 2.0, 3.0),
+            mass: 10.0,
+            ..Default::default()
+        };
+        let bodies = [
+            body,
+            body,
+            Object {
+                pos: Vec3::ZERO,
+                ..body
+            },
+        ];
+
+        for softening in [0.0, 0.001] {
+            let mut solvers: Vec<Box<dyn GravitySolver>> = vec![
+                Box::new(DirectSolver::new(softening)),
+                Box::new(DirectSolver::serial(softening)),
+                Box::new(BarnesHutSolver::new(0.5, softening)),
+            ];
+
+            for solver in solvers.iter_mut() {
+                let accelerations = solver.get_accelerations(&bodies);
+                assert!(
+                    accelerations.iter().all(|a| a.is_finite()),
+                    "softening {}: {:?}",
+                    softening,
+                    accelerations
+                );
+                // the third body is pulled by both coincident ones
+                assert!(accelerations[2].length() > 0.0);
+            }
+        }
+    }
+}
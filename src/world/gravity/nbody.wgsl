@@ -0,0 +1,79 @@
+/// Same as `Params` from `gravity/gpu.rs`
+struct Params {
+    amount: u32,
+    gravity_constant: f32,
+    // squared Plummer softening length
+    softening_2: f32,
+    _pad: u32,
+};
+
+struct Body {
+    pos: vec3<f32>,
+    mass: f32,
+};
+
+@group(0)
+@binding(0)
+var<uniform> params: Params;
+
+@group(0)
+@binding(1)
+var<storage, read> bodies: array<Body>;
+
+@group(0)
+@binding(2)
+var<storage, read_write> accelerations: array<vec4<f32>>;
+
+// must match workgroup size
+var<workgroup> tile: array<Body, 64>;
+
+/// All pairs, bodies are loaded into workgroup memory by tiles of 64,
+/// so each body is read from the storage buffer once per workgroup instead of once per invocation.
+@compute
+@workgroup_size(64)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+) {
+    let index = global_id.x;
+
+    // invocations past the end still load tiles, so no early return before barriers
+    var pos = vec3<f32>(0.0);
+    if (index < params.amount) {
+        pos = bodies[index].pos;
+    }
+
+    var a = vec3<f32>(0.0);
+    let tiles = (params.amount + 63u) / 64u;
+
+    for (var t = 0u; t < tiles; t = t + 1u) {
+        let j = t * 64u + local_id.x;
+        if (j < params.amount) {
+            tile[local_id.x] = bodies[j];
+        } else {
+            tile[local_id.x] = Body(vec3<f32>(0.0), 0.0);
+        }
+
+        workgroupBarrier();
+
+        for (var k = 0u; k < 64u; k = k + 1u) {
+            let other = t * 64u + k;
+            if (other == index || other >= params.amount) {
+                continue;
+            }
+
+            // Plummer softened a = G * m * delta / (r^2 + e^2)^(3/2)
+            let delta = tile[k].pos - pos;
+            let r_2 = dot(delta, delta) + params.softening_2;
+            if (r_2 > 0.0) {
+                a = a + delta * (tile[k].mass / r_2 * params.gravity_constant / sqrt(r_2));
+            }
+        }
+
+        workgroupBarrier();
+    }
+
+    if (index < params.amount) {
+        accelerations[index] = vec4<f32>(a, 0.0);
+    }
+}
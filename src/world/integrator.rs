@@ -0,0 +1,214 @@
+use glam::Vec3;
+
+use super::gravity::GravitySolver;
+use crate::{config::IntegratorKind, object::Object};
+
+/// Advances positions and velocities of bodies under their mutual gravity.
+pub trait Integrator: Send {
+    fn step(&mut self, bodies: &mut [Object], gravity: &mut dyn GravitySolver, dt: f32);
+}
+
+pub fn create_integrator(kind: IntegratorKind) -> Box<dyn Integrator> {
+    match kind {
+        IntegratorKind::Euler => Box::new(Euler),
+        IntegratorKind::SemiImplicitEuler => Box::new(SemiImplicitEuler),
+        IntegratorKind::Leapfrog => Box::new(Leapfrog::default()),
+        IntegratorKind::Rk4 => Box::new(Rk4),
+    }
+}
+
+/// Explicit Euler, position is moved with the old velocity. Energy grows, only for comparison.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Euler;
+
+impl Integrator for Euler {
+    fn step(&mut self, bodies: &mut [Object], gravity: &mut dyn GravitySolver, dt: f32) {
+        let accelerations = gravity.get_accelerations(bodies);
+
+        for (body, a) in bodies.iter_mut().zip(accelerations) {
+            body.pos += body.vel * dt;
+            body.vel += a * dt;
+        }
+    }
+}
+
+/// Symplectic (semi-implicit) Euler, position is moved with the new velocity.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SemiImplicitEuler;
+
+impl Integrator for SemiImplicitEuler {
+    fn step(&mut self, bodies: &mut [Object], gravity: &mut dyn GravitySolver, dt: f32) {
+        let accelerations = gravity.get_accelerations(bodies);
+
+        for (body, a) in bodies.iter_mut().zip(accelerations) {
+            body.vel += a * dt;
+            body.pos += body.vel * dt;
+        }
+    }
+}
+
+/// Kick-drift-kick leapfrog (velocity Verlet), second order and symplectic.
+///
+/// Accelerations at the end of a step are reused at the start of the next one,
+/// so there is only one gravity evaluation per step.
+#[derive(Debug, Default, Clone)]
+pub struct Leapfrog {
+    accelerations: Vec<Vec3>,
+}
+
+impl Integrator for Leapfrog {
+    fn step(&mut self, bodies: &mut [Object], gravity: &mut dyn GravitySolver, dt: f32) {
+        // bodies were added or removed since the last step
+        if self.accelerations.len() != bodies.len() {
+            self.accelerations = gravity.get_accelerations(bodies);
+        }
+
+        for (body, a) in bodies.iter_mut().zip(self.accelerations.iter()) {
+            body.vel += *a * (dt * 0.5);
+            body.pos += body.vel * dt;
+        }
+
+        self.accelerations = gravity.get_accelerations(bodies);
+
+        for (body, a) in bodies.iter_mut().zip(self.accelerations.iter()) {
+            body.vel += *a * (dt * 0.5);
+        }
+    }
+}
+
+/// Classic 4th order Runge-Kutta, accurate for short runs but not symplectic.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Rk4;
+
+impl Integrator for Rk4 {
+    fn step(&mut self, bodies: &mut [Object], gravity: &mut dyn GravitySolver, dt: f32) {
+        let mut stage = bodies.to_vec();
+
+        // derivatives of position (velocity) and velocity (acceleration) of each stage
+        let mut k_pos: Vec<Vec<Vec3>> = Vec::with_capacity(4);
+        let mut k_vel: Vec<Vec<Vec3>> = Vec::with_capacity(4);
+
+        for stage_dt in [0.0, dt * 0.5, dt * 0.5, dt] {
+            if let (Some(pos), Some(vel)) = (k_pos.last(), k_vel.last()) {
+                for (i, body) in stage.iter_mut().enumerate() {
+                    body.pos = bodies[i].pos + pos[i] * stage_dt;
+                    body.vel = bodies[i].vel + vel[i] * stage_dt;
+                }
+            }
+
+            k_vel.push(gravity.get_accelerations(&stage));
+            k_pos.push(stage.iter().map(|body| body.vel).collect());
+        }
+
+        for (i, body) in bodies.iter_mut().enumerate() {
+            body.pos +=
+                (k_pos[0][i] + 2.0 * k_pos[1][i] + 2.0 * k_pos[2][i] + k_pos[3][i]) * (dt / 6.0);
+            body.vel +=
+                (k_vel[0][i] + 2.0 * k_vel[1][i] + 2.0 * k_vel[2][i] + k_vel[3][i]) * (dt / 6.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        physics_constants::GRAVITY_CONSTANT_UNIT,
+        world::gravity::{get_total_energy, DirectSolver},
+    };
+    use std::f32::consts::PI;
+
+    /// Steps per orbit of `create_binary`.
+    const PERIOD: f32 = 200.0;
+
+    /// Two equal bodies on a circular orbit with unit separation.
+    fn create_binary() -> Vec<Object> {
+        // ω² = G * 2m / d³
+        let mass = (2.0 * PI / PERIOD).powi(2) / (2.0 * GRAVITY_CONSTANT_UNIT);
+        let speed = PI / PERIOD;
+
+        vec![
+            Object {
+                pos: Vec3::new(-0.5, 0.0, 0.0),
+                vel: Vec3::new(0.0, -speed, 0.0),
+                mass,
+                ..Default::default()
+            },
+            Object {
+                pos: Vec3::new(0.5, 0.0, 0.0),
+                vel: Vec3::new(0.0, speed, 0.0),
+                mass,
+                ..Default::default()
+            },
+        ]
+    }
+
+    /// Relative energy drift after each of `orbits`.
+    fn get_energy_drift(kind: IntegratorKind, orbits: usize) -> Vec<f64> {
+        let mut bodies = create_binary();
+        let mut gravity = DirectSolver::new(0.0);
+        let mut integrator = create_integrator(kind);
+        let initial_energy = get_total_energy(&bodies, 0.0);
+
+        (0..orbits)
+            .map(|_| {
+                for _ in 0..PERIOD as usize {
+                    integrator.step(&mut bodies, &mut gravity, 1.0);
+                }
+                ((get_total_energy(&bodies, 0.0) - initial_energy) / initial_energy.abs()).abs()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn leapfrog_energy_drift_is_bounded() {
+        let leapfrog = get_energy_drift(IntegratorKind::Leapfrog, 10);
+        let euler = get_energy_drift(IntegratorKind::Euler, 10);
+
+        assert!(
+            leapfrog.iter().all(|drift| *drift < 1e-3),
+            "leapfrog drift {:?}",
+            leapfrog
+        );
+        assert!(
+            euler.windows(2).all(|drift| drift[1] > drift[0]),
+            "euler drift {:?}",
+            euler
+        );
+        assert!(euler[9] > 100.0 * leapfrog[9], "euler drift {:?}", euler);
+    }
+
+    #[test]
+    fn semi_implicit_euler_and_rk4_keep_circular_orbit() {
+        // first order semi-implicit Euler keeps a slightly eccentric orbit, about ω * dt
+        for (kind, tolerance) in [
+            (IntegratorKind::SemiImplicitEuler, 0.05),
+            (IntegratorKind::Rk4, 1e-4),
+        ] {
+            let drift = get_energy_drift(kind, 10);
+            assert!(
+                drift.iter().all(|drift| *drift < 1e-3),
+                "{:?} drift {:?}",
+                kind,
+                drift
+            );
+
+            // separation of the binary is checked after every step, not only after full orbits
+            let mut bodies = create_binary();
+            let mut gravity = DirectSolver::new(0.0);
+            let mut integrator = create_integrator(kind);
+            let mut max_error: f32 = 0.0;
+            for _ in 0..10 * PERIOD as usize {
+                integrator.step(&mut bodies, &mut gravity, 1.0);
+                let separation = bodies[0].pos.distance(bodies[1].pos);
+                max_error = max_error.max((separation - 1.0).abs());
+            }
+            assert!(
+                max_error < tolerance,
+                "{:?} separation error {}",
+                kind,
+                max_error
+            );
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use super::PhotonBackend;
+use crate::{camera::CameraModel, canvas::Canvas, object::GravityWell, photons::Photon};
+use bytemuck::Zeroable;
+use rayon::prelude::*;
+use std::ops::Range;
+
+/// Rayon based photons processor, used when there is no wgpu adapter available.
+#[derive(Default)]
+pub struct CpuLightProcessor {
+    photons: Vec<Photon>,
+}
+
+impl CpuLightProcessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl PhotonBackend for CpuLightProcessor {
+    fn resize(&mut self, capacity: usize) {
+        self.photons.resize(capacity, Photon::zeroed());
+    }
+
+    fn emit(&mut self, slots: &[Range<usize>], photons: &[Photon]) {
+        let mut offset = 0;
+        for range in slots {
+            self.photons[range.clone()].copy_from_slice(&photons[offset..offset + range.len()]);
+            offset += range.len();
+        }
+    }
+
+    fn process_light(
+        &mut self,
+        camera: &dyn CameraModel,
+        canvas: &mut Canvas,
+        gravity_wells: &[GravityWell],
+    ) {
+        let hits: Vec<_> = self
+            .photons
+            .par_iter_mut()
+            .filter_map(|photon| {
+                if !photon.is_alive() {
+                    return None;
+                }
+
+                *photon = photon.process(gravity_wells);
+
+                if !photon.is_alive() {
+                    return None;
+                }
+
+                camera
+                    .get_intersection(*photon)
+                    .map(|(uv, factor)| (uv, photon.get_wavelength(), factor))
+            })
+            .collect();
+
+        for (uv, wavelength, factor) in hits {
+            canvas.update_pixel_by_uv(1. - uv, wavelength, 1.0 - factor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{camera::Camera, photons::wavelength::WaveLength};
+    use glam::{Vec2, Vec3};
+
+    #[test]
+    fn photon_through_pinhole_hits_canvas() {
+        // camera at the origin looking to -Z, the hole is at (0, 0, -1)
+        let camera = Camera::new(0.01, 1.0, Vec2::new(2.0, 2.0));
+
+        // the photon passes through the center of the hole and hits the sensor at (-0.75, -0.75)
+        let dir = Vec3::new(-0.75, -0.75, 1.0).normalize();
+        let start = Vec3::new(0.15, 0.15, -1.2);
+        let photon = Photon::new(WaveLength::WHITE, start, dir, 10);
+
+        let processed = photon.process(&[]);
+        assert!(processed.get_position().abs_diff_eq(start + dir, 1e-6));
+        assert!(processed.get_direction().abs_diff_eq(dir, 1e-6));
+        assert_eq!(processed.age, 1);
+
+        let mut processor = CpuLightProcessor::new();
+        processor.resize(1);
+        processor.emit(std::slice::from_ref(&(0..1)), &[photon]);
+
+        let mut canvas = Canvas::new(4, 4);
+        processor.process_light(&camera, &mut canvas, &[]);
+
+        assert_eq!(
+            processor.photons[0].get_position(),
+            processed.get_position()
+        );
+
+        // sensor uv (0.125, 0.125) is flipped by the hole into the opposite corner
+        for x in 0..4 {
+            for y in 0..4 {
+                let pixel = canvas.get_pixel_xyz(x, y);
+                if (x, y) == (3, 3) {
+                    assert!(pixel.abs_diff_eq(WaveLength::WHITE.to_xyz(), 1e-6));
+                } else {
+                    assert_eq!(pixel, Vec3::ZERO, "pixel ({}, {})", x, y);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,574 @@
+use super::PhotonBackend;
+use crate::{
+    camera::{CameraModel, CameraParams},
+    canvas::Canvas,
+    object::GravityWell,
+    photons::{
+        cie::{CMF_START, CMF_STEP, COLOR_MATCHING_FUNCTIONS},
+        Photon,
+    },
+    physics_constants::{GRAVITY_CONSTANT_UNIT, TIME_SPEED},
+};
+use bytemuck::{Pod, Zeroable};
+use futures::executor::block_on;
+use glam::Vec3;
+use std::{borrow::Cow, ops::Range, sync::Arc};
+use wgpu::util::DeviceExt;
+
+/// wgpu device and photons pipeline, created once and shared between all light groups
+/// (and the gpu gravity solver).
+pub struct GpuContext {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+
+    compute_pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+}
+
+impl GpuContext {
+    /// Return None if there is no suitable wgpu adapter on this machine.
+    pub async fn with_fallback_adapter(force_fallback_adapter: bool) -> Option<Self> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                // software adapter, e.g. to validate shaders on machines without gpu
+                force_fallback_adapter,
+                ..Default::default()
+            })
+            .await?;
+
+        // photons pool could be much larger than downlevel limits allow
+        let adapter_limits = adapter.limits();
+        let limits = wgpu::Limits {
+            max_buffer_size: adapter_limits.max_buffer_size,
+            max_storage_buffer_binding_size: adapter_limits.max_storage_buffer_binding_size,
+            ..wgpu::Limits::downlevel_defaults()
+        };
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits,
+                },
+                None,
+            )
+            .await
+            .ok()?;
+
+        let cs_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
+        });
+
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: None,
+            module: &cs_module,
+            entry_point: "main",
+        });
+
+        let bind_group_layout = compute_pipeline.get_bind_group_layout(0);
+
+        Some(GpuContext {
+            device,
+            queue,
+            compute_pipeline,
+            bind_group_layout,
+        })
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+}
+
+/// Photons pool buffer, recreated when the pool grows.
+struct PhotonsBuffer {
+    capacity: usize,
+    buffer: wgpu::Buffer,
+}
+
+/// Camera's sensor where photons are accumulated, recreated when canvas size changes.
+/// Each pixel is a fixed point CIE XYZ tristimulus.
+struct SensorBuffers {
+    width: u32,
+    height: u32,
+
+    sensor_buffer: wgpu::Buffer,
+    staging_buffer: wgpu::Buffer,
+}
+
+/// Stars positions and masses, recreated when there are more stars than it can hold.
+struct GravityWellsBuffer {
+    capacity: usize,
+    buffer: wgpu::Buffer,
+}
+
+pub struct GpuLightProcessor {
+    context: Arc<GpuContext>,
+
+    params_buffer: wgpu::Buffer,
+    color_matching_buffer: wgpu::Buffer,
+    gravity_wells: GravityWellsBuffer,
+    photons: Option<PhotonsBuffer>,
+    sensor: Option<SensorBuffers>,
+
+    /// Invalidated each time one of the buffers is recreated
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+/// Fixed point scale of values accumulated in the sensor buffer.
+/// The buffer is cleared every iteration, during one iteration a pixel saturates
+/// at `u32::MAX / SENSOR_SCALE` (about a million white photons).
+const SENSOR_SCALE: f32 = 4096.0;
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable, Default)]
+struct Params {
+    size: u32,
+    amount: u32,
+    time_speed: f32,
+    sensor_scale: f32,
+
+    sensor_width: u32,
+    sensor_height: u32,
+    gravity_constant: f32,
+    gravity_wells_amount: u32,
+
+    cmf_start: f32,
+    cmf_step: f32,
+    cmf_amount: u32,
+    _pad: u32,
+
+    camera: CameraParams,
+}
+
+impl PhotonsBuffer {
+    fn new(device: &wgpu::Device, capacity: usize) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Storage Buffer"),
+            size: Self::bytes_for(capacity),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        PhotonsBuffer { capacity, buffer }
+    }
+
+    /// Side of the square grid of invocations which covers all photons.
+    fn size_sq(&self) -> u32 {
+        (self.capacity as f32).sqrt().ceil() as u32
+    }
+
+    fn bytes_for(capacity: usize) -> wgpu::BufferAddress {
+        (capacity * std::mem::size_of::<Photon>()) as wgpu::BufferAddress
+    }
+}
+
+impl SensorBuffers {
+    fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let size = Self::bytes_for(width, height);
+
+        let sensor_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sensor Buffer"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        SensorBuffers {
+            width,
+            height,
+            sensor_buffer,
+            staging_buffer,
+        }
+    }
+
+    fn bytes_for(width: u32, height: u32) -> wgpu::BufferAddress {
+        (width as usize * height as usize * 3 * std::mem::size_of::<u32>()) as wgpu::BufferAddress
+    }
+}
+
+impl GravityWellsBuffer {
+    fn new(device: &wgpu::Device, capacity: usize) -> Self {
+        // empty buffers can't be bound, so there is always at least one slot
+        let capacity = capacity.max(1);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Gravity Wells Buffer"),
+            size: (capacity * std::mem::size_of::<GravityWell>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        GravityWellsBuffer { capacity, buffer }
+    }
+}
+
+impl GpuLightProcessor {
+    pub fn new(context: Arc<GpuContext>) -> Self {
+        let params_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Params Buffer"),
+            size: std::mem::size_of::<Params>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let color_matching_buffer =
+            context
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Color Matching Buffer"),
+                    contents: bytemuck::cast_slice(&COLOR_MATCHING_FUNCTIONS),
+                    usage: wgpu::BufferUsages::STORAGE,
+                });
+
+        let gravity_wells = GravityWellsBuffer::new(&context.device, 0);
+
+        GpuLightProcessor {
+            context,
+            params_buffer,
+            color_matching_buffer,
+            gravity_wells,
+            photons: None,
+            sensor: None,
+            bind_group: None,
+        }
+    }
+
+    fn update_sensor(&mut self, width: u32, height: u32) {
+        if let Some(sensor) = &self.sensor {
+            if sensor.width == width && sensor.height == height {
+                return;
+            }
+        }
+
+        self.sensor = Some(SensorBuffers::new(&self.context.device, width, height));
+        self.bind_group = None;
+    }
+
+    fn update_gravity_wells(&mut self, gravity_wells: &[GravityWell]) {
+        if gravity_wells.len() > self.gravity_wells.capacity {
+            self.gravity_wells = GravityWellsBuffer::new(&self.context.device, gravity_wells.len());
+            self.bind_group = None;
+        }
+
+        if gravity_wells.is_empty() {
+            return;
+        }
+
+        self.context.queue.write_buffer(
+            &self.gravity_wells.buffer,
+            0,
+            bytemuck::cast_slice(gravity_wells),
+        );
+    }
+
+    async fn process(
+        &mut self,
+        camera: &dyn CameraModel,
+        canvas: &mut Canvas,
+        gravity_wells: &[GravityWell],
+    ) {
+        if self.photons.is_none() {
+            return;
+        }
+
+        self.update_sensor(canvas.width(), canvas.height());
+        self.update_gravity_wells(gravity_wells);
+
+        let (photons, sensor) = match (&self.photons, &self.sensor) {
+            (Some(photons), Some(sensor)) => (photons, sensor),
+            _ => return,
+        };
+        let device = &self.context.device;
+
+        let bind_group = self.bind_group.get_or_insert_with(|| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &self.context.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: self.params_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: photons.buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: sensor.sensor_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: self.gravity_wells.buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: self.color_matching_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        self.context.queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::cast_slice(&[Params {
+                size: photons.size_sq(),
+                amount: photons.capacity as u32,
+                time_speed: TIME_SPEED,
+                sensor_scale: SENSOR_SCALE,
+                sensor_width: sensor.width,
+                sensor_height: sensor.height,
+                gravity_constant: GRAVITY_CONSTANT_UNIT,
+                gravity_wells_amount: gravity_wells.len() as u32,
+                cmf_start: CMF_START,
+                cmf_step: CMF_STEP,
+                cmf_amount: COLOR_MATCHING_FUNCTIONS.len() as u32,
+                _pad: 0,
+                camera: camera.get_shader_params(),
+            }]),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.clear_buffer(&sensor.sensor_buffer, 0, None);
+        {
+            let workgroups = photons.size_sq().div_ceil(16);
+
+            let mut compute_pass =
+                encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+            compute_pass.set_pipeline(&self.context.compute_pipeline);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            compute_pass.insert_debug_marker("compute photons");
+            compute_pass.dispatch_workgroups(workgroups, workgroups, 1);
+        }
+
+        encoder.copy_buffer_to_buffer(
+            &sensor.sensor_buffer,
+            0,
+            &sensor.staging_buffer,
+            0,
+            SensorBuffers::bytes_for(sensor.width, sensor.height),
+        );
+
+        self.context.queue.submit(Some(encoder.finish()));
+
+        let buffer_slice = sensor.staging_buffer.slice(..);
+
+        let (sender, receiver) = futures_intrusive::channel::shared::oneshot_channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |v| sender.send(v).unwrap());
+
+        device.poll(wgpu::Maintain::Wait);
+
+        if let Some(Ok(())) = receiver.receive().await {
+            let data = buffer_slice.get_mapped_range();
+            let result: &[u32] = bytemuck::cast_slice(&data);
+
+            for (i, pixel) in result.chunks_exact(3).enumerate() {
+                if pixel.iter().all(|value| *value == 0) {
+                    continue;
+                }
+
+                let x = i as u32 % sensor.width;
+                let y = i as u32 / sensor.width;
+                let xyz = Vec3::new(pixel[0] as f32, pixel[1] as f32, pixel[2] as f32);
+                canvas.update_pixel_xyz(x, y, xyz / SENSOR_SCALE);
+            }
+
+            drop(data);
+            sensor.staging_buffer.unmap();
+        } else {
+            panic!("Failed to map buffer");
+        }
+    }
+}
+
+impl PhotonBackend for GpuLightProcessor {
+    fn resize(&mut self, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+
+        let device = &self.context.device;
+        let photons = PhotonsBuffer::new(device, capacity);
+
+        if let Some(old_photons) = self.photons.take() {
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            encoder.copy_buffer_to_buffer(
+                &old_photons.buffer,
+                0,
+                &photons.buffer,
+                0,
+                PhotonsBuffer::bytes_for(old_photons.capacity.min(capacity)),
+            );
+            self.context.queue.submit(Some(encoder.finish()));
+        }
+
+        self.photons = Some(photons);
+        self.bind_group = None;
+    }
+
+    fn emit(&mut self, slots: &[Range<usize>], photons: &[Photon]) {
+        // nothing emitted yet, the pool may not be allocated
+        if slots.is_empty() {
+            return;
+        }
+
+        let buffer = &self
+            .photons
+            .as_ref()
+            .expect("Photons buffer allocated")
+            .buffer;
+
+        let mut offset = 0;
+        for range in slots {
+            self.context.queue.write_buffer(
+                buffer,
+                PhotonsBuffer::bytes_for(range.start),
+                bytemuck::cast_slice(&photons[offset..offset + range.len()]),
+            );
+            offset += range.len();
+        }
+    }
+
+    fn process_light(
+        &mut self,
+        camera: &dyn CameraModel,
+        canvas: &mut Canvas,
+        gravity_wells: &[GravityWell],
+    ) {
+        block_on(async { self.process(camera, canvas, gravity_wells).await });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::{Camera, LensCamera},
+        object::Object,
+        test_utils,
+        world::light_processing::PhotonPool,
+    };
+    use glam::Vec2;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn empty_emission_is_not_allocated() {
+        let Some(context) = test_utils::gpu_context() else {
+            return;
+        };
+
+        let mut pool = PhotonPool::new(Some(&context));
+        let camera = Camera::new(0.01, 1.0, Vec2::new(2.0, 2.0));
+        let mut canvas = Canvas::new(4, 4);
+
+        // e.g. a scene with only black holes
+        for _ in 0..3 {
+            pool.emit(&[], 10);
+            pool.process_light(&camera, &mut canvas, &[]);
+        }
+
+        assert_eq!(canvas.get_pixel_xyz(0, 0), Vec3::ZERO);
+    }
+
+    /// Canvas after a few iterations of photons from stars around a black hole.
+    fn render(gpu_context: Option<&Arc<GpuContext>>, camera: &dyn CameraModel) -> Canvas {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let stars: Vec<Object> = (0..8)
+            .map(|i| Object {
+                pos: Vec3::new(i as f32 * 0.1 - 0.4, (i % 3) as f32 * 0.1, -(i % 2) as f32),
+                ..Default::default()
+            })
+            .collect();
+        let gravity_wells = [GravityWell::from(&Object::black_hole(
+            Vec3::new(0.2, 0.0, 0.5),
+            1e11,
+        ))];
+
+        let mut pool = PhotonPool::new(gpu_context);
+        let mut canvas = Canvas::new(32, 32);
+        for _ in 0..6 {
+            let mut photons = vec![];
+            for star in stars.iter() {
+                star.spawn_photons(&mut rng, 10, &mut photons);
+            }
+
+            pool.emit(&photons, 10);
+            pool.process_light(camera, &mut canvas, &gravity_wells);
+        }
+
+        canvas
+    }
+
+    #[test]
+    fn matches_cpu_backend() {
+        let Some(context) = test_utils::gpu_context() else {
+            return;
+        };
+
+        let mut pinhole = Camera::new(0.5, 1.0, Vec2::new(2.0, 2.0));
+        let mut lens = LensCamera::new(0.3, 1.0, 2.0, Vec2::new(2.0, 2.0));
+        let cameras: [&mut dyn CameraModel; 2] = [&mut pinhole, &mut lens];
+
+        for camera in cameras {
+            camera.set_position(Vec3::new(0.0, 0.0, 2.0));
+            camera.look_at(Vec3::ZERO, Vec3::Y);
+
+            let cpu = render(None, camera);
+            let gpu = render(Some(&context), camera);
+
+            let mut total = Vec3::ZERO;
+            let mut difference = Vec3::ZERO;
+            let mut total_difference = Vec3::ZERO;
+            for x in 0..cpu.width() {
+                for y in 0..cpu.height() {
+                    let (cpu, gpu) = (cpu.get_pixel_xyz(x, y), gpu.get_pixel_xyz(x, y));
+                    total += cpu;
+                    total_difference += gpu - cpu;
+                    difference += (gpu - cpu).abs();
+                }
+            }
+
+            // only the fixed point rounding of the sensor differs
+            let kind = camera.get_shader_params().kind;
+            assert!(total.min_element() > 0.0, "no photons hit the sensor");
+            assert!(
+                total_difference.abs().cmple(total * 1e-4).all(),
+                "camera {}: total {} differs by {}",
+                kind,
+                total,
+                total_difference
+            );
+            assert!(
+                difference.cmple(total * 1e-3).all(),
+                "camera {}: pixels of {} differ by {}",
+                kind,
+                total,
+                difference
+            );
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{
+    camera::CameraModel, canvas::Canvas, config::LightBackend, object::GravityWell, photons::Photon,
+};
+use futures::executor::block_on;
+use std::{ops::Range, sync::Arc};
+
+use self::photon_slots::PhotonSlots;
+pub use self::{
+    cpu::CpuLightProcessor,
+    gpu::{GpuContext, GpuLightProcessor},
+};
+
+mod cpu;
+mod gpu;
+mod photon_slots;
+
+/// Storage of all photons in the world.
+pub trait PhotonBackend: Send {
+    /// Grow the pool up to `capacity` photons, keeping existing ones.
+    fn resize(&mut self, capacity: usize);
+
+    /// Write photons into the given slots of the pool.
+    fn emit(&mut self, slots: &[Range<usize>], photons: &[Photon]);
+
+    /// Move all alive photons one step forward and draw those which pass through the camera.
+    fn process_light(
+        &mut self,
+        camera: &dyn CameraModel,
+        canvas: &mut Canvas,
+        gravity_wells: &[GravityWell],
+    );
+}
+
+/// Create the wgpu context for the configured backend (None means photons are processed on CPU).
+/// `force_fallback_adapter` requests the software adapter instead of a real gpu.
+pub fn create_gpu_context(
+    backend: LightBackend,
+    force_fallback_adapter: bool,
+) -> Option<Arc<GpuContext>> {
+    let new_context = || block_on(GpuContext::with_fallback_adapter(force_fallback_adapter));
+    let context = match backend {
+        LightBackend::Cpu => None,
+        LightBackend::Gpu => Some(new_context().expect("wgpu device created")),
+        LightBackend::Auto => {
+            let context = new_context();
+            if context.is_none() {
+                log::warn!("No wgpu adapter found, falling back to CPU light processing");
+            }
+            context
+        }
+    };
+
+    context.map(Arc::new)
+}
+
+/// Single pool of photons of all ages, dead photons slots are reused by newly emitted ones.
+pub struct PhotonPool {
+    slots: PhotonSlots,
+    backend: Box<dyn PhotonBackend>,
+}
+
+impl PhotonPool {
+    pub fn new(gpu_context: Option<&Arc<GpuContext>>) -> Self {
+        let backend: Box<dyn PhotonBackend> = match gpu_context {
+            Some(context) => Box::new(GpuLightProcessor::new(context.clone())),
+            None => Box::new(CpuLightProcessor::new()),
+        };
+
+        PhotonPool {
+            slots: PhotonSlots::new(),
+            backend,
+        }
+    }
+
+    /// Put new photons into the pool, `ttl` - amount of iterations they will live.
+    pub fn emit(&mut self, photons: &[Photon], ttl: usize) {
+        let capacity = self.slots.capacity();
+        let slots = self.slots.allocate(photons.len(), ttl);
+        if self.slots.capacity() != capacity {
+            self.backend.resize(self.slots.capacity());
+        }
+
+        self.backend.emit(&slots, photons);
+    }
+
+    pub fn process_light(
+        &mut self,
+        camera: &dyn CameraModel,
+        canvas: &mut Canvas,
+        gravity_wells: &[GravityWell],
+    ) {
+        self.backend.process_light(camera, canvas, gravity_wells);
+        self.slots.next_iteration();
+    }
+}
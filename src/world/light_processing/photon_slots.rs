@@ -0,0 +1,137 @@
+use std::ops::Range;
+
+/// Keeps track of occupied slots of the photons pool, so new photons reuse the slots of dead ones.
+#[derive(Debug, Default)]
+pub struct PhotonSlots {
+    capacity: usize,
+    iteration: u64,
+
+    free: Vec<Range<usize>>,
+
+    /// Occupied slot ranges with the iteration at which their photons are dead
+    occupied: Vec<(u64, Range<usize>)>,
+}
+
+impl PhotonSlots {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Move to the next iteration and release slots of photons which are dead by now.
+    pub fn next_iteration(&mut self) {
+        self.iteration += 1;
+
+        let iteration = self.iteration;
+        let free = &mut self.free;
+        self.occupied.retain(|(dead_at, range)| {
+            if *dead_at <= iteration {
+                free.push(range.clone());
+                return false;
+            }
+            true
+        });
+    }
+
+    /// Allocate `count` slots for photons living `ttl` iterations.
+    /// Capacity is (at least) doubled if there are not enough free slots.
+    pub fn allocate(&mut self, count: usize, ttl: usize) -> Vec<Range<usize>> {
+        let dead_at = self.iteration + ttl as u64;
+        let mut ranges = vec![];
+        let mut left = count;
+
+        while left > 0 {
+            let range = match self.free.pop() {
+                Some(range) if range.len() > left => {
+                    self.free.push(range.start + left..range.end);
+                    range.start..range.start + left
+                }
+                Some(range) => range,
+                None => {
+                    let start = self.capacity;
+                    self.capacity = (self.capacity * 2).max(self.capacity + left);
+                    if self.capacity > start + left {
+                        self.free.push(start + left..self.capacity);
+                    }
+                    start..start + left
+                }
+            };
+
+            left -= range.len();
+            self.occupied.push((dead_at, range.clone()));
+            ranges.push(range);
+        }
+
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Slot indices of `ranges`, sorted.
+    fn indices(ranges: &[Range<usize>]) -> Vec<usize> {
+        let mut indices: Vec<usize> = ranges.iter().cloned().flatten().collect();
+        indices.sort_unstable();
+        indices
+    }
+
+    #[test]
+    fn allocates_into_free_slots() {
+        let mut slots = PhotonSlots::new();
+
+        assert_eq!(slots.allocate(3, 10), vec![0..3]);
+        assert_eq!(slots.capacity(), 3);
+
+        // capacity is doubled, the rest of the new slots stays free
+        assert_eq!(slots.allocate(1, 10), vec![3..4]);
+        assert_eq!(slots.capacity(), 6);
+
+        assert_eq!(slots.allocate(2, 10), vec![4..6]);
+        assert_eq!(slots.capacity(), 6);
+    }
+
+    #[test]
+    fn grows_when_full() {
+        let mut slots = PhotonSlots::new();
+        slots.allocate(4, 10);
+
+        // doubling is not enough, capacity grows to fit exactly
+        let ranges = slots.allocate(10, 10);
+        assert_eq!(indices(&ranges), (4..14).collect::<Vec<_>>());
+        assert_eq!(slots.capacity(), 14);
+
+        assert_eq!(slots.allocate(0, 10), vec![]);
+        assert_eq!(slots.capacity(), 14);
+    }
+
+    #[test]
+    fn reuses_slots_of_dead_photons() {
+        let mut slots = PhotonSlots::new();
+        let short_lived = slots.allocate(4, 1);
+        let long_lived = slots.allocate(4, 3);
+        let capacity = slots.capacity();
+
+        // photons living one iteration are dead after it
+        slots.next_iteration();
+        let reused = slots.allocate(4, 3);
+        assert_eq!(indices(&reused), indices(&short_lived));
+        assert_eq!(slots.capacity(), capacity);
+
+        // all slots are occupied again
+        slots.next_iteration();
+        let grown = slots.allocate(1, 3);
+        assert_eq!(slots.capacity(), capacity * 2);
+        assert!(indices(&grown).iter().all(|index| *index >= capacity));
+
+        // the second group dies at the third iteration
+        slots.next_iteration();
+        let reused = slots.allocate(4, 3);
+        assert_eq!(indices(&reused), indices(&long_lived));
+        assert_eq!(slots.capacity(), capacity * 2);
+    }
+}
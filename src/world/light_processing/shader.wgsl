@@ -0,0 +1,271 @@
+/// Same as `CameraParams` from `camera/mod.rs`
+struct Camera {
+    position: vec4<f32>,
+    // rotation from world space to camera space
+    inverse_rotation: vec4<f32>,
+    sensor_size: vec2<f32>,
+    // 0 - camera obscura, 1 - thin lens
+    kind: u32,
+    aperture_radius: f32,
+    focal_length: f32,
+    image_distance: f32,
+};
+
+struct Params {
+    size: u32,
+    amount: u32,
+    time_speed: f32,
+    sensor_scale: f32,
+
+    sensor_width: u32,
+    sensor_height: u32,
+    gravity_constant: f32,
+    gravity_wells_amount: u32,
+
+    cmf_start: f32,
+    cmf_step: f32,
+    cmf_amount: u32,
+
+    camera: Camera,
+};
+
+struct GravityWell {
+    pos: vec3<f32>,
+    mass: f32,
+    absorption_radius: f32,
+}
+
+struct Photon {
+    pos: vec4<f32>,
+    dir: vec4<f32>,
+    wavelength: f32,
+    age: u32,
+    ttl: u32,
+}
+
+@group(0)
+@binding(0)
+var<uniform> params : Params;
+
+@group(0)
+@binding(1)
+var<storage, read_write> v_indices: array<Photon>; // this is used as both input and output for convenience
+
+/// fixed point CIE XYZ tristimulus for each pixel
+@group(0)
+@binding(2)
+var<storage, read_write> sensor: array<atomic<u32>>;
+
+@group(0)
+@binding(3)
+var<storage, read> gravity_wells: array<GravityWell>;
+
+/// CIE 1931 color matching functions, three values (x, y, z) for each wavelength
+@group(0)
+@binding(4)
+var<storage, read> color_matching: array<f32>;
+
+/// Same as `wavelength_to_xyz` from `photons/cie.rs`
+fn wavelength_to_xyz(wavelength: f32) -> vec3<f32> {
+    let position = (wavelength - params.cmf_start) / params.cmf_step;
+    if (position < 0.0 || position > f32(params.cmf_amount - 1u)) {
+        return vec3<f32>(0.0, 0.0, 0.0);
+    }
+
+    let index = min(u32(position), params.cmf_amount - 2u);
+    let t = position - f32(index);
+
+    let a = vec3<f32>(color_matching[index * 3u], color_matching[index * 3u + 1u], color_matching[index * 3u + 2u]);
+    let b = vec3<f32>(color_matching[index * 3u + 3u], color_matching[index * 3u + 4u], color_matching[index * 3u + 5u]);
+
+    return mix(a, b, t);
+}
+
+/// Same as `get_light_gravity_acceleration` from `physics_constants.rs`
+fn get_light_gravity_acceleration(other_mass: f32, r_2: f32) -> f32 {
+    return 2.0 * other_mass / r_2 * params.gravity_constant;
+}
+
+/// Same as `get_segment_distance_squared` from `photons/mod.rs`
+fn get_segment_distance_squared(start: vec3<f32>, delta: vec3<f32>, center: vec3<f32>) -> f32 {
+    let t = clamp(dot(center - start, delta) / dot(delta, delta), 0.0, 1.0);
+    let d = start + delta * t - center;
+    return dot(d, d);
+}
+
+/// Same as `Photon::process`
+fn process(photon: Photon) -> Photon {
+    if (photon.age >= photon.ttl) {
+        return photon;
+    }
+
+    let pos = photon.pos.xyz;
+    var dir = photon.dir.xyz;
+
+    for (var i = 0u; i < params.gravity_wells_amount; i = i + 1u) {
+        let well = gravity_wells[i];
+        let delta = well.pos - pos;
+        let r_2 = dot(delta, delta);
+        if (r_2 > 0.0) {
+            dir = dir + normalize(delta) * get_light_gravity_acceleration(well.mass, r_2) * params.time_speed;
+        }
+    }
+
+    dir = normalize(dir);
+    let movement = dir * params.time_speed;
+
+    var age = photon.age + 1u;
+    for (var i = 0u; i < params.gravity_wells_amount; i = i + 1u) {
+        let well = gravity_wells[i];
+        let radius = well.absorption_radius;
+        if (radius > 0.0 && get_segment_distance_squared(pos, movement, well.pos) < radius * radius) {
+            age = photon.ttl;
+        }
+    }
+
+    return Photon (
+        vec4<f32>(pos + movement, photon.pos.w),
+        vec4<f32>(dir, photon.dir.w),
+        photon.wavelength,
+        age,
+        photon.ttl,
+    );
+}
+
+/// Rotate vector `v` by quaternion `q`
+fn rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    let t = 2.0 * cross(q.xyz, v);
+    return v + q.w * t + cross(q.xyz, t);
+}
+
+/// Same as `CameraModel::get_intersection` of `Camera`, `pos` and `dir` are in camera space
+fn get_pinhole_intersection(pos: vec3<f32>, dir: vec3<f32>) -> vec3<f32> {
+    let miss = vec3<f32>(0.0, 0.0, -1.0);
+    let camera = params.camera;
+
+    let factor = -pos.z;
+    let sensor_overlap_position = pos + dir / dir.z * factor;
+
+    let uv = sensor_overlap_position.xy / camera.sensor_size;
+
+    // check if overlap point is belongs to sensor rectangle
+    if (abs(uv.x) > 0.5 || abs(uv.y) > 0.5) {
+        return miss;
+    }
+
+    let hole_overlap_position = sensor_overlap_position - dir / dir.z * camera.focal_length;
+    let hole_overlap_uv = hole_overlap_position.xy / camera.aperture_radius;
+
+    let dist_sq = dot(hole_overlap_uv, hole_overlap_uv);
+    if (dist_sq > 1.0) {
+        return miss;
+    }
+
+    return vec3<f32>(uv + 0.5, dist_sq);
+}
+
+/// Same as `CameraModel::get_intersection` of `LensCamera`, `pos` and `dir` are in camera space
+fn get_lens_intersection(pos: vec3<f32>, dir: vec3<f32>) -> vec3<f32> {
+    let miss = vec3<f32>(0.0, 0.0, -1.0);
+    let camera = params.camera;
+
+    // point where the photon crosses the lens plane
+    let lens_overlap_position = pos + dir * ((-camera.image_distance - pos.z) / dir.z);
+    let lens_overlap_uv = lens_overlap_position.xy / camera.aperture_radius;
+
+    let dist_sq = dot(lens_overlap_uv, lens_overlap_uv);
+    if (dist_sq > 1.0) {
+        return miss;
+    }
+
+    // thin lens changes the slope of the ray proportionally to the distance from its center
+    let slope = dir.xy / dir.z - lens_overlap_position.xy / camera.focal_length;
+    let sensor_overlap_position = lens_overlap_position.xy + slope * camera.image_distance;
+
+    let uv = sensor_overlap_position / camera.sensor_size;
+
+    // check if overlap point is belongs to sensor rectangle
+    if (abs(uv.x) > 0.5 || abs(uv.y) > 0.5) {
+        return miss;
+    }
+
+    return vec3<f32>(uv + 0.5, dist_sq);
+}
+
+/// Same as `CameraModel::get_intersection`: returns sensor uv and accuracy,
+/// accuracy is negative if photon does not pass through the camera.
+fn get_intersection(photon: Photon) -> vec3<f32> {
+    let camera = params.camera;
+
+    // to camera space
+    let pos = rotate(camera.inverse_rotation, photon.pos.xyz - camera.position.xyz);
+    let dir = normalize(rotate(camera.inverse_rotation, photon.dir.xyz));
+
+    if (pos.z >= 0.0 || pos.z + dir.z < 0.0) {
+        return vec3<f32>(0.0, 0.0, -1.0);
+    }
+
+    if (camera.kind == 1u) {
+        return get_lens_intersection(pos, dir);
+    }
+
+    return get_pinhole_intersection(pos, dir);
+}
+
+/// `atomicAdd` which sticks to the largest u32 instead of wrapping around.
+/// The thread whose addition wraps sees it from the old value and saturates the sum,
+/// later additions to the saturated value wrap as well and saturate it again.
+fn add_saturating(index: u32, value: u32) {
+    let old = atomicAdd(&sensor[index], value);
+    if (old > 0xffffffffu - value) {
+        atomicMax(&sensor[index], 0xffffffffu);
+    }
+}
+
+/// Same as `Canvas::update_pixel_by_uv`, but pixels are accumulated without y flip.
+fn update_sensor(uv: vec2<f32>, wavelength: f32, luminosity: f32) {
+    if (uv.x < 0.0 || uv.x >= 1.0 || uv.y < 0.0 || uv.y >= 1.0) {
+        return;
+    }
+
+    let x = u32(uv.x * f32(params.sensor_width));
+    let y = u32(uv.y * f32(params.sensor_height));
+    if (x >= params.sensor_width || y >= params.sensor_height) {
+        return;
+    }
+
+    // rounded, so small contributions are not biased towards zero
+    let xyz = vec3<u32>(round(wavelength_to_xyz(wavelength) * luminosity * params.sensor_scale));
+    let index = (x + y * params.sensor_width) * 3u;
+
+    add_saturating(index, xyz.x);
+    add_saturating(index + 1u, xyz.y);
+    add_saturating(index + 2u, xyz.z);
+}
+
+@compute
+@workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if (global_id.x >= params.size || global_id.y >= params.size) {
+        return;
+    }
+
+    let index: u32 = global_id.x + global_id.y * params.size;
+    if (index >= params.amount) {
+        return;
+    }
+
+    let photon = process(v_indices[index]);
+    v_indices[index] = photon;
+
+    if (photon.age >= photon.ttl) {
+        return;
+    }
+
+    let intersection = get_intersection(photon);
+    if (intersection.z < 0.0) {
+        return;
+    }
+
+    update_sensor(1.0 - intersection.xy, photon.wavelength, 1.0 - intersection.z);
+}
@@ -1,114 +1,385 @@
 use crate::{
-    camera::Camera,
+    camera::CameraModel,
     canvas::Canvas,
-    config::CONFIG,
-    object::Object,
-    physics_constants::{get_gravity_acceleration, TIME_SPEED},
+    config::{GravitySolverKind, LightBackend, CONFIG},
+    object::{GravityWell, Object},
+    photons::Photon,
+    physics_constants::TIME_SPEED,
 };
-use futures::executor::block_on;
 use glam::Vec3;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use rayon::prelude::*;
-use std::{
-    collections::LinkedList,
-    sync::{Arc, Mutex},
-};
+use std::{path::Path, sync::Arc};
 
-use self::light_processing::LightProcessor;
+use self::{
+    gravity::{create_gravity_solver, GravitySolver},
+    integrator::{create_integrator, Integrator},
+    light_processing::PhotonPool,
+    scene::{CameraSettings, RenderSettings, Scene, SceneError},
+};
 
+mod collisions;
+pub mod gravity;
+pub mod integrator;
 mod light_processing;
+pub mod scene;
 mod spawn_galaxy;
 
+pub use self::light_processing::{create_gpu_context, GpuContext};
+
 pub struct World {
-    /// Represent all photons for each frame
-    light_groups: LinkedList<LightProcessor>,
+    /// All photons in the world
+    photons: PhotonPool,
     stars: Vec<Object>,
+    gravity: Box<dyn GravitySolver>,
+    integrator: Box<dyn Integrator>,
+    /// Source of all randomness, so runs with the same seed are identical
+    rng: ChaCha8Rng,
+    /// Device shared by photons and stars gravity, None if there is no adapter or it isn't needed
+    gpu_context: Option<Arc<GpuContext>>,
+    camera: CameraSettings,
+    render: RenderSettings,
 }
 
 impl World {
+    /// World with the default scene (a single galaxy).
     pub fn new() -> World {
+        World::with_scene(&Scene::default())
+    }
+
+    /// World described by a RON scene file.
+    pub fn from_scene<T: AsRef<Path>>(path: T) -> Result<World, SceneError> {
+        Ok(World::with_scene(&Scene::load(path)?))
+    }
+
+    pub fn with_scene(scene: &Scene) -> World {
+        World::with_seed(
+            scene,
+            CONFIG.seed.unwrap_or_else(rand::random),
+            CONFIG.light_backend,
+            CONFIG.gpu_fallback_adapter,
+        )
+    }
+
+    /// Same as `with_scene`, but seed, photons backend and the software adapter choice
+    /// are passed explicitly instead of `CONFIG`.
+    pub fn with_seed(
+        scene: &Scene,
+        seed: u64,
+        light_backend: LightBackend,
+        force_fallback_adapter: bool,
+    ) -> World {
+        log::info!("Simulation seed: {}", seed);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
         let mut stars = vec![];
 
-        spawn_galaxy::spawn_galaxy(
-            &mut stars,
-            Vec3::new(0., 0., -4.),
-            // Vec3::new(1.0, 3.0, 2.0),
-            Vec3::Z,
-            0.2,
-            0.01,
-            1000,
-            (0.8, 2000.0),
-        );
+        for object in scene.objects.iter() {
+            object.spawn(&mut rng, &mut stars);
+        }
 
-        // stars.push(Object {
-        //     pos: Vec3::new(0., 0., -4.),
-        //     ..Default::default()
-        // });
+        // one device for photons and stars gravity
+        let gpu_context = match (light_backend, CONFIG.gravity_solver) {
+            (LightBackend::Cpu, GravitySolverKind::Gpu) => {
+                create_gpu_context(LightBackend::Auto, force_fallback_adapter)
+            }
+            (backend, _) => create_gpu_context(backend, force_fallback_adapter),
+        };
+        let photons_gpu_context = match light_backend {
+            LightBackend::Cpu => None,
+            _ => gpu_context.as_ref(),
+        };
 
         World {
-            light_groups: LinkedList::new(),
+            photons: PhotonPool::new(photons_gpu_context),
             stars,
+            gravity: create_gravity_solver(
+                CONFIG.gravity_solver,
+                CONFIG.barnes_hut_theta,
+                CONFIG.gravity_softening,
+                gpu_context.as_ref(),
+            ),
+            integrator: create_integrator(CONFIG.integrator),
+            rng,
+            gpu_context,
+            camera: scene.camera,
+            render: scene.render,
         }
     }
 
-    pub fn update_light(&mut self, camera: Camera, canvas: &mut Canvas) {
-        let total_photons_count = self
-            .stars
+    /// wgpu device of the world, to run other gpu work without creating a second one.
+    pub fn gpu_context(&self) -> Option<&Arc<GpuContext>> {
+        self.gpu_context.as_ref()
+    }
+
+    /// Camera described by the scene.
+    pub fn camera_settings(&self) -> &CameraSettings {
+        &self.camera
+    }
+
+    /// Render settings described by the scene.
+    pub fn render_settings(&self) -> &RenderSettings {
+        &self.render
+    }
+
+    /// All stars and black holes in the world.
+    pub fn stars(&self) -> &[Object] {
+        &self.stars
+    }
+
+    /// Center of mass of all objects in the world.
+    pub fn get_center_of_mass(&self) -> Vec3 {
+        let total_mass: f32 = self.stars.iter().map(|star| star.mass).sum();
+        if total_mass == 0.0 {
+            return Vec3::ZERO;
+        }
+
+        self.stars
             .iter()
-            .map(|star| star.get_photons_per_frame())
-            .sum::<usize>();
+            .fold(Vec3::ZERO, |sum, star| sum + star.pos * star.mass)
+            / total_mass
+    }
+
+    pub fn update_light(&mut self, camera: &dyn CameraModel, canvas: &mut Canvas) {
+        // every star gets its own stream of the frame seed,
+        // so the result doesn't depend on how rayon splits the work
+        let frame_seed: u64 = self.rng.gen();
+        let ttl = self.render.photons_ttl.unwrap_or(CONFIG.photons_ttl);
+        let frame_photons: Vec<Photon> = self
+            .stars
+            .par_iter()
+            .enumerate()
+            .flat_map_iter(|(i, star)| {
+                let mut rng = ChaCha8Rng::seed_from_u64(frame_seed);
+                rng.set_stream(i as u64);
 
-        let mut frame_photons = Vec::with_capacity(total_photons_count);
+                let mut photons = Vec::with_capacity(star.get_photons_per_frame());
+                star.spawn_photons(&mut rng, ttl, &mut photons);
+                photons
+            })
+            .collect();
 
-        self.stars.iter().for_each(|star| {
-            star.spawn_photons(&mut frame_photons);
-        });
+        self.photons.emit(&frame_photons, ttl);
+        let gravity_wells: Vec<GravityWell> = if CONFIG.light_gravity {
+            self.stars
+                .iter()
+                .filter(|star| star.is_gravity_well(CONFIG.light_gravity_min_mass))
+                .map(GravityWell::from)
+                .collect()
+        } else {
+            vec![]
+        };
 
-        self.light_groups
-            .push_back(block_on(async { LightProcessor::new(frame_photons).await }));
+        self.photons.process_light(camera, canvas, &gravity_wells);
+    }
 
-        let canvas = Arc::new(Mutex::new(canvas));
+    pub fn update_movement(&mut self) {
+        self.integrator
+            .step(&mut self.stars, self.gravity.as_mut(), TIME_SPEED);
 
-        self.light_groups
-            .par_iter_mut()
-            .for_each(|light_processor| {
-                block_on(async {
-                    let canvas = canvas.clone();
-                    light_processor
-                        .process_light_for_group(camera, canvas)
-                        .await;
-                });
-            });
+        let count = self.stars.len();
+        self.stars
+            .retain(|star| star.pos.is_finite() && star.vel.is_finite());
+        if self.stars.len() != count {
+            log::warn!(
+                "Removed {} stars with invalid position or velocity",
+                count - self.stars.len()
+            );
+        }
 
-        if self.light_groups.len() > CONFIG.photons_ttl {
-            self.light_groups.pop_front();
+        if CONFIG.star_merging {
+            let merged = collisions::merge_close_bodies(&mut self.stars, CONFIG.merge_distance);
+            if merged > 0 {
+                log::info!("Merged {} stars", merged);
+            }
         }
     }
+}
 
-    pub fn update_movement(&mut self) {
-        self.stars = self
-            .stars
-            .iter()
-            .enumerate()
-            .map(|(i, star)| {
-                let mut a = Vec3::ZERO;
+impl Default for World {
+    fn default() -> Self {
+        World::new()
+    }
+}
 
-                for (j, other_star) in self.stars.iter().enumerate() {
-                    if i == j {
-                        continue;
-                    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{canvas::Canvas, world::scene::SceneObject};
 
-                    let delta = other_star.pos - star.pos;
+    fn create_scene() -> Scene {
+        let mut scene = Scene::default();
+        // close camera with a wide hole, so a lot of photons hit the sensor
+        scene.camera.position = Vec3::new(0.0, 0.0, 2.0);
+        scene.camera.hole_size = Some(0.5);
+        scene.render.width = 64;
+        scene.render.height = 64;
+        scene.objects = vec![SceneObject::Galaxy {
+            center: Vec3::ZERO,
+            top: Vec3::Z,
+            radius: 0.2,
+            thickness: 0.01,
+            size: 20,
+            mass_range: (0.8, 2000.0),
+        }];
+        scene
+    }
 
-                    a += delta.normalize()
-                        * get_gravity_acceleration(other_star.mass, delta.length_squared());
-                }
+    /// Canvas after `iterations` light and movement updates.
+    fn render(world: &mut World, iterations: usize) -> Canvas {
+        let camera = world
+            .camera_settings()
+            .create_camera(world.get_center_of_mass());
+        let mut canvas = Canvas::new(
+            world.render_settings().width,
+            world.render_settings().height,
+        );
+
+        for _ in 0..iterations {
+            world.update_light(camera.as_ref(), &mut canvas);
+            world.update_movement();
+        }
+
+        canvas
+    }
+
+    #[test]
+    fn same_seed_gives_same_result() {
+        let scene = create_scene();
+        let mut first = World::with_seed(&scene, 42, LightBackend::Cpu, false);
+        let mut second = World::with_seed(&scene, 42, LightBackend::Cpu, false);
 
-                Object {
-                    pos: star.pos + star.vel * TIME_SPEED,
-                    vel: star.vel + a,
-                    ..*star
+        let first_canvas = render(&mut first, 5);
+        let second_canvas = render(&mut second, 5);
+
+        for x in 0..first_canvas.width() {
+            for y in 0..first_canvas.height() {
+                assert_eq!(
+                    first_canvas.get_pixel_xyz(x, y),
+                    second_canvas.get_pixel_xyz(x, y),
+                    "pixel ({}, {})",
+                    x,
+                    y
+                );
+            }
+        }
+        assert!(lit_pixels(&first_canvas) > 0, "no photons hit the sensor");
+
+        for (a, b) in first.stars().iter().zip(second.stars()) {
+            assert_eq!(a.pos, b.pos);
+            assert_eq!(a.vel, b.vel);
+        }
+    }
+
+    fn lit_pixels(canvas: &Canvas) -> usize {
+        let mut lit = 0;
+        for x in 0..canvas.width() {
+            for y in 0..canvas.height() {
+                if canvas.get_pixel_xyz(x, y) != Vec3::ZERO {
+                    lit += 1;
                 }
-            })
+            }
+        }
+        lit
+    }
+
+    #[test]
+    fn bundled_scenes_build() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes");
+
+        let files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
             .collect();
+        assert!(!files.is_empty(), "no scenes in {:?}", dir);
+
+        for path in files {
+            let scene = Scene::load(&path).unwrap_or_else(|e| panic!("{:?}: {}", path, e));
+            let world = World::with_seed(&scene, 0, LightBackend::Cpu, false);
+
+            assert!(!world.stars().is_empty(), "{:?} is empty", path);
+        }
+    }
+
+    #[test]
+    fn black_hole_scene_reaches_camera() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/black_hole.ron");
+        let mut scene = Scene::load(path).unwrap();
+        scene.render.width = 64;
+        scene.render.height = 64;
+        let mut world = World::with_seed(&scene, 0, LightBackend::Cpu, false);
+
+        let canvas = render(&mut world, 15);
+
+        assert!(lit_pixels(&canvas) > 0, "no photons hit the sensor");
+    }
+
+    #[test]
+    fn missing_scene_is_io_error() {
+        let result = World::from_scene("scenes/does_not_exist.ron");
+
+        assert!(matches!(result, Err(SceneError::Io(_))));
+    }
+
+    #[test]
+    fn galaxy_mass_range_must_be_positive() {
+        let galaxy = |mass_range: &str| {
+            format!(
+                "Scene(objects: [Galaxy(center: (0.0, 0.0, 0.0), top: (0.0, 0.0, 1.0), \
+                 radius: 0.2, thickness: 0.01, size: 5, mass_range: {})])",
+                mass_range
+            )
+        };
+
+        for mass_range in ["(0.0, 10.0)", "(-1.0, 10.0)", "(10.0, 1.0)", "(1.0, inf)"] {
+            let result: Result<Scene, _> = galaxy(mass_range).parse();
+            assert!(
+                matches!(result, Err(SceneError::Invalid(_))),
+                "{}",
+                mass_range
+            );
+        }
+
+        let scene: Scene = galaxy("(1.0, 1.0)").parse().unwrap();
+        let world = World::with_seed(&scene, 0, LightBackend::Cpu, false);
+        assert!(world.stars().iter().all(|star| star.mass == 1.0));
+    }
+
+    #[test]
+    fn render_size_must_not_be_zero() {
+        for render in ["(width: 0)", "(height: 0)"] {
+            let result: Result<Scene, _> = format!("Scene(render: {})", render).parse();
+            assert!(matches!(result, Err(SceneError::Invalid(_))), "{}", render);
+        }
+    }
+
+    #[test]
+    fn galaxy_top_can_be_any_direction() {
+        let galaxy = |top: &str| {
+            format!(
+                "Scene(objects: [Galaxy(center: (0.0, 0.0, 0.0), top: {}, \
+                 radius: 0.2, thickness: 0.01, size: 5, mass_range: (1.0, 10.0))])",
+                top
+            )
+        };
+
+        for top in ["(0.0, 0.0, 0.0)", "(inf, 0.0, 0.0)", "(NaN, 0.0, 1.0)"] {
+            let result: Result<Scene, _> = galaxy(top).parse();
+            assert!(matches!(result, Err(SceneError::Invalid(_))), "{}", top);
+        }
+
+        // parallel to `Vec3::ONE`, which the disk basis must not depend on
+        for top in ["(1.0, 1.0, 1.0)", "(-0.5, -0.5, -0.5)", "(0.0, 0.0, 1.0)"] {
+            let scene: Scene = galaxy(top).parse().unwrap();
+            let world = World::with_seed(&scene, 0, LightBackend::Cpu, false);
+            assert_eq!(world.stars().len(), 5, "{}", top);
+            assert!(
+                world.stars().iter().all(|star| star.pos.is_finite()),
+                "{}",
+                top
+            );
+        }
     }
 }
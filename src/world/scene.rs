@@ -0,0 +1,279 @@
+use std::{fmt, fs, io, path::Path, str::FromStr};
+
+use glam::{Vec2, Vec3};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::{Camera, CameraModel, LensCamera},
+    config::{CameraKind, CONFIG},
+    object::{emission::EmissionProfile, Object, ObjectKind},
+    photons::spectrum::Spectrum,
+};
+
+use super::spawn_galaxy::{spawn_galaxy, Galaxy};
+
+/// Everything needed to render a world, usually loaded from a RON file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scene {
+    #[serde(default)]
+    pub camera: CameraSettings,
+    #[serde(default)]
+    pub render: RenderSettings,
+    #[serde(default)]
+    pub objects: Vec<SceneObject>,
+}
+
+/// Camera placement and optics, unset optics are taken from `CONFIG`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    pub kind: Option<CameraKind>,
+    pub position: Vec3,
+    /// Point the camera looks at, center of mass of the world if not set
+    pub target: Option<Vec3>,
+    pub up: Vec3,
+    pub focal_length: f32,
+    pub sensor_size: Vec2,
+    pub hole_size: Option<f32>,
+    pub aperture: Option<f32>,
+    pub focus_distance: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    pub width: u32,
+    pub height: u32,
+    /// Amount of iterations photons live, `PHOTONS_TTL` if not set.
+    /// Must be larger than the distance from the light sources to the camera
+    pub photons_ttl: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SceneObject {
+    Star {
+        pos: Vec3,
+        #[serde(default)]
+        vel: Vec3,
+        #[serde(default = "default_mass")]
+        mass: f32,
+        #[serde(default)]
+        spectrum: Spectrum,
+        #[serde(default)]
+        emission: EmissionProfile,
+        #[serde(default = "default_luminosity")]
+        luminosity: f32,
+    },
+    BlackHole {
+        pos: Vec3,
+        #[serde(default)]
+        vel: Vec3,
+        mass: f32,
+    },
+    /// Disk of randomly placed stars, see `spawn_galaxy`
+    Galaxy {
+        center: Vec3,
+        /// Normal of the galaxy disk
+        top: Vec3,
+        radius: f32,
+        thickness: f32,
+        size: usize,
+        mass_range: (f32, f32),
+    },
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    /// Parsed, but some values can't be simulated
+    Invalid(String),
+}
+
+fn default_mass() -> f32 {
+    1.0
+}
+
+fn default_luminosity() -> f32 {
+    1.0
+}
+
+impl Scene {
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Scene, SceneError> {
+        let source = fs::read_to_string(path).map_err(SceneError::Io)?;
+        source.parse()
+    }
+
+    /// Check values which can't be expressed by their types.
+    pub fn validate(&self) -> Result<(), SceneError> {
+        let RenderSettings { width, height, .. } = self.render;
+        if width == 0 || height == 0 {
+            return Err(SceneError::Invalid(format!(
+                "render size must not be zero, got {}x{}",
+                width, height
+            )));
+        }
+
+        for object in self.objects.iter() {
+            if let SceneObject::Galaxy {
+                top, mass_range, ..
+            } = object
+            {
+                // normal of the disk, the stars are placed in the plane orthogonal to it
+                if !(top.is_finite() && *top != Vec3::ZERO) {
+                    return Err(SceneError::Invalid(format!(
+                        "galaxy top must be a finite non zero vector, got {:?}",
+                        top
+                    )));
+                }
+
+                // masses are drawn from a power law, which is infinite at zero
+                let (min, max) = *mass_range;
+                if !(min > 0.0 && max.is_finite() && min <= max) {
+                    return Err(SceneError::Invalid(format!(
+                        "galaxy mass_range must satisfy 0 < min <= max, got {:?}",
+                        mass_range
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Scene {
+    type Err = SceneError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let scene: Scene = ron::from_str(s).map_err(SceneError::Parse)?;
+        scene.validate()?;
+        Ok(scene)
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene {
+            camera: CameraSettings::default(),
+            render: RenderSettings::default(),
+            objects: vec![SceneObject::Galaxy {
+                center: Vec3::ZERO,
+                top: Vec3::Z,
+                radius: 0.2,
+                thickness: 0.01,
+                size: 1000,
+                mass_range: (0.8, 2000.0),
+            }],
+        }
+    }
+}
+
+impl SceneObject {
+    pub fn spawn<R: Rng>(&self, rng: &mut R, objects: &mut Vec<Object>) {
+        match *self {
+            SceneObject::Star {
+                pos,
+                vel,
+                mass,
+                spectrum,
+                emission,
+                luminosity,
+            } => objects.push(Object {
+                kind: ObjectKind::Star,
+                pos,
+                vel,
+                mass,
+                spectrum,
+                emission,
+                luminosity,
+            }),
+            SceneObject::BlackHole { pos, vel, mass } => objects.push(Object {
+                vel,
+                ..Object::black_hole(pos, mass)
+            }),
+            SceneObject::Galaxy {
+                center,
+                top,
+                radius,
+                thickness,
+                size,
+                mass_range,
+            } => spawn_galaxy(
+                rng,
+                objects,
+                &Galaxy {
+                    center,
+                    top,
+                    radius,
+                    thickness,
+                    size,
+                    mass_range,
+                },
+            ),
+        }
+    }
+}
+
+impl CameraSettings {
+    /// Create the camera and point it to the target (or to `center_of_mass`).
+    pub fn create_camera(&self, center_of_mass: Vec3) -> Box<dyn CameraModel> {
+        let mut camera: Box<dyn CameraModel> = match self.kind.unwrap_or(CONFIG.camera_kind) {
+            CameraKind::Pinhole => Box::new(Camera::new(
+                self.hole_size.unwrap_or(CONFIG.camera_hole_size),
+                self.focal_length,
+                self.sensor_size,
+            )),
+            CameraKind::Lens => Box::new(LensCamera::new(
+                self.aperture.unwrap_or(CONFIG.camera_aperture),
+                self.focal_length,
+                self.focus_distance.unwrap_or(CONFIG.camera_focus_distance),
+                self.sensor_size,
+            )),
+        };
+
+        camera.set_position(self.position);
+        camera.look_at(self.target.unwrap_or(center_of_mass), self.up);
+
+        camera
+    }
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            kind: None,
+            position: Vec3::new(0.0, 0.0, 8.0),
+            target: None,
+            up: Vec3::Y,
+            focal_length: 1.0,
+            sensor_size: Vec2::new(2.0, 2.0),
+            hole_size: None,
+            aperture: None,
+            focus_distance: None,
+        }
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            width: 512,
+            height: 512,
+            photons_ttl: None,
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "Failed to read scene: {}", e),
+            SceneError::Parse(e) => write!(f, "Failed to parse scene: {}", e),
+            SceneError::Invalid(e) => write!(f, "Invalid scene: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
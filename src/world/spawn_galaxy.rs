@@ -1,40 +1,113 @@
 use std::f32::consts::PI;
 
 use glam::Vec3;
+use rand::Rng;
+
+use crate::{
+    config::CONFIG, object::Object, photons::spectrum::Spectrum,
+    physics_constants::get_main_sequence_temperature,
+};
+
+/// Exponent of the Salpeter initial mass function dN/dm ~ m^-2.35.
+const SALPETER_EXPONENT: f32 = 2.35;
+
+/// Disk of stars with random radius and mass.
+#[derive(Debug, Clone, Copy)]
+pub struct Galaxy {
+    pub center: Vec3,
+    /// Normal of the galaxy disk
+    pub top: Vec3,
+    pub radius: f32,
+    pub thickness: f32,
+    /// Amount of stars
+    pub size: usize,
+    /// Masses are drawn from the Salpeter initial mass function inside of this range,
+    /// so most of the stars are light (red and yellow) and only a few are heavy (blue)
+    pub mass_range: (f32, f32),
+}
+
+pub fn spawn_galaxy<R: Rng>(rng: &mut R, stars: &mut Vec<Object>, galaxy: &Galaxy) {
+    let Galaxy {
+        center,
+        top,
+        radius,
+        thickness,
+        size,
+        mass_range,
+    } = *galaxy;
 
-use crate::{object::Object, photons::wavelength::WaveLength};
-
-pub fn spawn_galaxy(
-    stars: &mut Vec<Object>,
-    center: Vec3,
-    top: Vec3,
-    radius: f32,
-    thickness: f32,
-    size: usize,
-    mass_range: (f32, f32),
-) {
     let z = top.normalize();
-    let x = top.cross(top + Vec3::ONE).normalize();
-    let y = top.cross(x).normalize();
+    let x = z.any_orthonormal_vector();
+    let y = z.cross(x);
 
     for i in 0..size {
         let angle = i as f32 * 2.0 * PI / size as f32;
 
-        let r: f32 = rand::random();
+        let r: f32 = rng.gen();
         // let r = 1.0 - r * r;
         let r = r * radius;
 
-        let position = center
-            + x * r * angle.cos()
-            + y * r * angle.sin()
-            + z * rand::random::<f32>() * thickness;
+        let position =
+            center + x * r * angle.cos() + y * r * angle.sin() + z * rng.gen::<f32>() * thickness;
+
+        let mass = sample_salpeter_mass(rng, mass_range);
+
+        let temperature = if CONFIG.temperature_from_mass {
+            get_main_sequence_temperature(mass)
+        } else {
+            rng.gen::<f32>() * (CONFIG.max_star_temperature - CONFIG.min_star_temperature)
+                + CONFIG.min_star_temperature
+        };
 
         let star = Object {
-            pos: center + position,
-            mass: rand::random::<f32>() * (mass_range.1 - mass_range.0) + mass_range.0,
-            photons_wavelength: WaveLength(rand::random::<f32>() * 370.0 + 380.0),
+            pos: position,
+            mass,
+            spectrum: Spectrum::BlackBody(
+                temperature.clamp(CONFIG.min_star_temperature, CONFIG.max_star_temperature),
+            ),
             ..Default::default()
         };
         stars.push(star);
     }
 }
+
+/// Mass from the Salpeter initial mass function truncated to `range` (inverse transform sampling).
+fn sample_salpeter_mass<R: Rng>(rng: &mut R, (min, max): (f32, f32)) -> f32 {
+    let power = 1.0 - SALPETER_EXPONENT;
+    let min_power = min.powf(power);
+    let max_power = max.powf(power);
+
+    let t: f32 = rng.gen();
+    (min_power + t * (max_power - min_power)).powf(1.0 / power)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics_constants::get_main_sequence_temperature;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn salpeter_masses_are_mostly_light() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let range = (0.8, 2000.0);
+        let mut masses: Vec<f32> = (0..10_000)
+            .map(|_| sample_salpeter_mass(&mut rng, range))
+            .collect();
+
+        assert!(masses.iter().all(|mass| (range.0..=range.1).contains(mass)));
+
+        // median of the truncated power law is 0.8 * 2^(1 / 1.35) ≈ 1.34
+        masses.sort_by(|a, b| a.total_cmp(b));
+        let median = masses[masses.len() / 2];
+        assert!((median - 1.34).abs() < 0.05, "median {}", median);
+
+        // hotter than 40 000 K, clamped to the default `MAX_STAR_TEMPERATURE`
+        let hottest = masses
+            .iter()
+            .filter(|mass| get_main_sequence_temperature(**mass) > 40_000.0)
+            .count();
+        assert!(hottest < masses.len() / 100, "{} hottest stars", hottest);
+    }
+}